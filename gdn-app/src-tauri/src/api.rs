@@ -1,15 +1,22 @@
 use std::sync::{Arc, Mutex};
 
-use gdn::ids::NoteId;
-use tauri::{AppHandle, Emitter, State};
+use gdn::{crypto::Secret, ids::NoteId, repo::Grade, store::Store};
+use jiff::Timestamp;
+use tauri::{
+    AppHandle, Emitter, State,
+    ipc::{Channel, InvokeResponseBody},
+};
 
 use crate::{
     state::AppState,
-    types::{EventNotesStoreUpdate, Note},
+    types::{Card, EventNotesStoreUpdate, Note, Operation},
 };
 
 // API methods are sorted alphabetically.
 
+/// Size of the chunks attachments are sent to the frontend in.
+const ATTACHMENT_CHUNK_SIZE: usize = 64 * 1024;
+
 fn update_if_required(state: &mut AppState, app: &AppHandle) {
     let store_id = state.store.id();
     if state.store_last_id == Some(store_id) {
@@ -22,6 +29,45 @@ fn update_if_required(state: &mut AppState, app: &AppHandle) {
     state.store_last_id = Some(store_id)
 }
 
+/// Send the content of an attachment to the frontend in chunks.
+///
+/// Returns the size of the attachment in bytes, so the frontend knows when
+/// all chunks have arrived.
+#[tauri::command]
+pub fn note_attachment_get(
+    id: NoteId,
+    name: String,
+    on_chunk: Channel<InvokeResponseBody>,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<usize, String> {
+    let hash = {
+        let guard = state.lock().unwrap();
+        let attachment = guard
+            .store
+            .attachment(id, &name)
+            .ok_or_else(|| format!("no attachment {name} on note {id}"))?;
+        attachment.hash.clone()
+    };
+
+    let load = || {
+        let data = gdn::data::open(gdn::data::path()?)?;
+        let state = gdn::data::load_state(&data)?;
+        let selected = state
+            .selected_repo
+            .ok_or_else(|| anyhow::anyhow!("no repo selected"))?;
+        gdn::data::load_attachment(&data, selected, &hash)
+    };
+    let content = load().map_err(|err| err.to_string())?;
+
+    for chunk in content.chunks(ATTACHMENT_CHUNK_SIZE) {
+        on_chunk
+            .send(InvokeResponseBody::Raw(chunk.to_vec()))
+            .map_err(|err| err.to_string())?;
+    }
+
+    Ok(content.len())
+}
+
 #[tauri::command]
 pub fn note_child_add(
     id: NoteId,
@@ -115,9 +161,152 @@ pub fn note_text_set(
     update_if_required(&mut guard, &app);
 }
 
+fn require_note(store: &Store, id: NoteId) -> Result<(), String> {
+    match store.get(id) {
+        Some(_) => Ok(()),
+        None => Err(format!("no note with id {id}")),
+    }
+}
+
+fn apply_operation(store: &mut Store, operation: Operation) -> Result<Option<NoteId>, String> {
+    match operation {
+        Operation::ChildAdd {
+            id,
+            child_id,
+            child_position,
+        } => {
+            // Like note_child_add, children that don't exist are ignored.
+            store
+                .add_child_at_position(id, child_id, child_position)
+                .ok_or_else(|| format!("no note with id {id}"))?;
+        }
+        Operation::ChildMove {
+            child_id,
+            from_id,
+            from_iteration,
+            to_id,
+            to_position,
+        } => {
+            require_note(store, from_id)?;
+            require_note(store, to_id)?;
+            store
+                .move_child_by_id_to_position(child_id, from_id, from_iteration, to_id, to_position)
+                .ok_or_else(|| format!("note {from_id} has no child {child_id}"))?;
+        }
+        Operation::ChildRemove {
+            id,
+            child_id,
+            child_iteration,
+        } => {
+            require_note(store, id)?;
+            store
+                .remove_child_by_id(id, child_id, child_iteration)
+                .ok_or_else(|| format!("note {id} has no child {child_id}"))?;
+        }
+        Operation::ChildrenSet { id, children } => {
+            require_note(store, id)?;
+            store.set_children(id, children);
+        }
+        Operation::Create { text } => return Ok(Some(store.create(text))),
+        Operation::Delete { id } => {
+            store
+                .delete(id)
+                .ok_or_else(|| format!("no note with id {id}"))?;
+        }
+        Operation::TextSet { id, text } => {
+            require_note(store, id)?;
+            store.set_text(id, text);
+        }
+    }
+    Ok(None)
+}
+
+/// Apply several operations at once, resulting in a single update.
+///
+/// If any operation fails, none of them are applied. Returns the ids of the
+/// created notes in order.
+#[tauri::command]
+pub fn notes_batch(
+    operations: Vec<Operation>,
+    app: AppHandle,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<Vec<NoteId>, String> {
+    let mut guard = state.lock().unwrap();
+    let created = guard.store.transaction(|store| {
+        let mut created = vec![];
+        for operation in operations {
+            created.extend(apply_operation(store, operation)?);
+        }
+        Ok::<_, String>(created)
+    })?;
+    update_if_required(&mut guard, &app);
+    Ok(created)
+}
+
+#[tauri::command]
+pub fn notes_by_tag(tag: String, state: State<'_, Arc<Mutex<AppState>>>) -> Vec<Note> {
+    let guard = state.lock().unwrap();
+    guard
+        .store
+        .notes_by_tag(&tag)
+        .into_iter()
+        .filter_map(|id| guard.store.get(id))
+        .map(Note::from)
+        .collect()
+}
+
+/// Move all notes to the trash, from where they can still be restored.
 #[tauri::command]
 pub fn notes_clear(app: AppHandle, state: State<'_, Arc<Mutex<AppState>>>) {
     let mut guard = state.lock().unwrap();
     guard.store.clear();
     update_if_required(&mut guard, &app);
 }
+
+/// Check a passphrase against the selected repo and remember it.
+///
+/// Returns whether the repo is encrypted.
+#[tauri::command]
+pub fn repo_unlock(
+    passphrase: String,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) -> Result<bool, String> {
+    let secret = Secret::from_passphrase(passphrase);
+
+    let unlock = || {
+        let data = gdn::data::open(gdn::data::path()?)?;
+        let state = gdn::data::load_state(&data)?;
+        let selected = state
+            .selected_repo
+            .ok_or_else(|| anyhow::anyhow!("no repo selected"))?;
+        match gdn::data::load_repo_scheme(&data, selected)? {
+            Some(scheme) => scheme.unlock(&secret).map(|_| true),
+            None => Ok(false),
+        }
+    };
+    let encrypted = unlock().map_err(|err| err.to_string())?;
+
+    state.lock().unwrap().secret = Some(secret);
+    Ok(encrypted)
+}
+
+#[tauri::command]
+pub fn review_due(new_limit: usize, state: State<'_, Arc<Mutex<AppState>>>) -> Vec<Card> {
+    let guard = state.lock().unwrap();
+    gdn::review::due_cards(&guard.store, Timestamp::now(), new_limit)
+        .into_iter()
+        .map(Card::from)
+        .collect()
+}
+
+#[tauri::command]
+pub fn review_grade(
+    id: NoteId,
+    grade: Grade,
+    app: AppHandle,
+    state: State<'_, Arc<Mutex<AppState>>>,
+) {
+    let mut guard = state.lock().unwrap();
+    guard.store.record_review(id, grade, Timestamp::now());
+    update_if_required(&mut guard, &app);
+}
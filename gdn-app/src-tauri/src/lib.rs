@@ -17,6 +17,7 @@ pub fn run() {
         .plugin(tauri_plugin_opener::init())
         .manage(state)
         .invoke_handler(tauri::generate_handler![
+            api::note_attachment_get,
             api::note_child_add,
             api::note_child_move,
             api::note_child_remove,
@@ -25,7 +26,12 @@ pub fn run() {
             api::note_delete,
             api::note_get,
             api::note_text_set,
+            api::notes_batch,
+            api::notes_by_tag,
             api::notes_clear,
+            api::repo_unlock,
+            api::review_due,
+            api::review_grade,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -1,8 +1,10 @@
-use gdn::store::Store;
+use gdn::{crypto::Secret, store::Store};
 
 pub struct AppState {
     pub store: Store,
     pub store_last_id: Option<u64>,
+    /// The secret to unlock the selected repo with, if it is encrypted.
+    pub secret: Option<Secret>,
 }
 
 impl AppState {
@@ -10,6 +12,7 @@ impl AppState {
         Self {
             store: Store::new(),
             store_last_id: None,
+            secret: None,
         }
     }
 }
@@ -1,7 +1,7 @@
 use std::collections::HashSet;
 
-use gdn::{ids::NoteId, store::RichNote};
-use serde::Serialize;
+use gdn::{ids::NoteId, repo, review, store::RichNote};
+use serde::{Deserialize, Serialize};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -10,6 +10,9 @@ pub struct Note {
     pub text: String,
     pub children: Vec<NoteId>,
     pub parents: HashSet<NoteId>,
+    pub links: Vec<NoteId>,
+    pub backlinks: HashSet<NoteId>,
+    pub attachments: Vec<Attachment>,
 }
 
 impl From<RichNote> for Note {
@@ -19,10 +22,93 @@ impl From<RichNote> for Note {
             text: value.text,
             children: value.children,
             parents: value.parents,
+            links: value.links,
+            backlinks: value.backlinks,
+            attachments: value
+                .meta
+                .attachments
+                .into_iter()
+                .map(|it| it.into())
+                .collect(),
         }
     }
 }
 
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Attachment {
+    pub name: String,
+    pub size: u64,
+}
+
+impl From<repo::Attachment> for Attachment {
+    fn from(value: repo::Attachment) -> Self {
+        Self {
+            name: value.name,
+            size: value.size,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Card {
+    pub id: NoteId,
+    pub front: String,
+    pub back: String,
+}
+
+impl From<review::Card> for Card {
+    fn from(value: review::Card) -> Self {
+        Self {
+            id: value.id,
+            front: value.front,
+            back: value.back,
+        }
+    }
+}
+
+/// A single operation of a [`crate::api::notes_batch`].
+#[derive(Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Operation {
+    ChildAdd {
+        id: NoteId,
+        child_id: NoteId,
+        child_position: isize,
+    },
+    ChildMove {
+        child_id: NoteId,
+        from_id: NoteId,
+        from_iteration: usize,
+        to_id: NoteId,
+        to_position: isize,
+    },
+    ChildRemove {
+        id: NoteId,
+        child_id: NoteId,
+        child_iteration: usize,
+    },
+    ChildrenSet {
+        id: NoteId,
+        children: Vec<NoteId>,
+    },
+    Create {
+        text: String,
+    },
+    Delete {
+        id: NoteId,
+    },
+    TextSet {
+        id: NoteId,
+        text: String,
+    },
+}
+
 ////////////
 // Events //
 ////////////
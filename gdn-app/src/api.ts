@@ -1,5 +1,27 @@
-import { invoke } from "@tauri-apps/api/core";
-import { Note } from "./types";
+import { Channel, invoke } from "@tauri-apps/api/core";
+import { z } from "zod/v4";
+import { Card, Grade, NodeId, Note, Operation } from "./types";
+
+export async function apiNoteAttachmentGet(id: string, name: string): Promise<Blob> {
+  const chunks: ArrayBuffer[] = [];
+  let received = 0;
+  let expected: number | undefined;
+  let done: () => void = () => {};
+  const complete = new Promise<void>((resolve) => (done = resolve));
+
+  const onChunk = new Channel<ArrayBuffer>();
+  onChunk.onmessage = (chunk) => {
+    chunks.push(chunk);
+    received += chunk.byteLength;
+    if (expected !== undefined && received >= expected) done();
+  };
+
+  expected = z.number().parse(await invoke("note_attachment_get", { id, name, onChunk }));
+  if (received >= expected) done();
+  await complete;
+
+  return new Blob(chunks);
+}
 
 export async function apiNoteChildAdd(
   id: string,
@@ -47,6 +69,26 @@ export async function apiNoteTextSet(id: string, text: string): Promise<void> {
   await invoke("note_text_set", { id, text });
 }
 
+export async function apiNotesBatch(operations: Operation[]): Promise<string[]> {
+  return NodeId.array().parse(await invoke("notes_batch", { operations }));
+}
+
+export async function apiNotesByTag(tag: string): Promise<Note[]> {
+  return Note.array().parse(await invoke("notes_by_tag", { tag }));
+}
+
 export async function apiNotesClear(): Promise<void> {
   await invoke("notes_clear");
 }
+
+export async function apiRepoUnlock(passphrase: string): Promise<boolean> {
+  return z.boolean().parse(await invoke("repo_unlock", { passphrase }));
+}
+
+export async function apiReviewDue(newLimit: number): Promise<Card[]> {
+  return Card.array().parse(await invoke("review_due", { newLimit }));
+}
+
+export async function apiReviewGrade(id: string, grade: Grade): Promise<void> {
+  await invoke("review_grade", { id, grade });
+}
@@ -6,11 +6,12 @@ import {
   apiNoteCreate,
   apiNoteDelete,
   apiNoteGet,
+  apiNotesBatch,
   apiNotesClear,
   apiNoteTextSet,
 } from "@/api";
 import { Segment } from "@/lib/path";
-import { EventNoteStoreUpdate } from "@/types";
+import { EventNoteStoreUpdate, Operation } from "@/types";
 import { listen } from "@tauri-apps/api/event";
 import { defineStore } from "pinia";
 import { ref } from "vue";
@@ -20,6 +21,9 @@ export interface Note {
   readonly text: string;
   readonly children: readonly string[];
   readonly parents: ReadonlySet<string>;
+  readonly links: readonly string[];
+  readonly backlinks: ReadonlySet<string>;
+  readonly attachments: readonly { readonly name: string; readonly size: number }[];
 }
 
 export const useNotesStore = defineStore("notes", () => {
@@ -76,6 +80,10 @@ export const useNotesStore = defineStore("notes", () => {
     return apiNoteChildMove(segment.id, fromId, segment.iteration, toId, toPosition);
   }
 
+  async function batch(operations: Operation[]): Promise<string[]> {
+    return apiNotesBatch(operations);
+  }
+
   async function clearNotes(): Promise<void> {
     return apiNotesClear();
   }
@@ -91,6 +99,7 @@ export const useNotesStore = defineStore("notes", () => {
     addChild,
     removeChild,
     moveChild,
+    batch,
     clearNotes,
   };
 });
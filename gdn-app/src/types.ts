@@ -3,14 +3,49 @@ import { z } from "zod/v4";
 export type NodeId = z.infer<typeof NodeId>;
 export const NodeId = z.string().startsWith("n").length(17);
 
+export type Attachment = z.infer<typeof Attachment>;
+export const Attachment = z.object({
+  name: z.string(),
+  size: z.number(),
+});
+
 export type Note = z.infer<typeof Note>;
 export const Note = z.object({
   id: NodeId,
   text: z.string(),
   children: z.array(NodeId),
   parents: z.array(NodeId).transform((it) => new Set(it)),
+  links: z.array(NodeId),
+  backlinks: z.array(NodeId).transform((it) => new Set(it)),
+  attachments: z.array(Attachment),
 });
 
+export type Card = z.infer<typeof Card>;
+export const Card = z.object({
+  id: NodeId,
+  front: z.string(),
+  back: z.string(),
+});
+
+export type Grade = z.infer<typeof Grade>;
+export const Grade = z.enum(["again", "hard", "good", "easy"]);
+
+export type Operation =
+  | { type: "childAdd"; id: string; childId: string; childPosition: number }
+  | {
+      type: "childMove";
+      childId: string;
+      fromId: string;
+      fromIteration: number;
+      toId: string;
+      toPosition: number;
+    }
+  | { type: "childRemove"; id: string; childId: string; childIteration: number }
+  | { type: "childrenSet"; id: string; children: string[] }
+  | { type: "create"; text: string }
+  | { type: "delete"; id: string }
+  | { type: "textSet"; id: string; text: string };
+
 ////////////
 // Events //
 ////////////
@@ -1,11 +1,23 @@
+use std::fmt::Display;
+
 use clap::Parser;
+use gdn::{
+    data::State,
+    ids::{NoteId, RepoId},
+};
 
 use crate::Environment;
 
+mod backup;
+mod export;
+mod import;
 mod note;
 mod repo;
+mod review;
 mod status;
+mod tag;
 mod tidy;
+mod trash;
 
 #[derive(Debug, Parser)]
 pub enum Command {
@@ -15,6 +27,20 @@ pub enum Command {
     #[command(visible_alias = "t")]
     Tidy(tidy::Command),
 
+    Review(review::Command),
+
+    #[command(subcommand)]
+    #[command(visible_alias = "b")]
+    Backup(backup::Command),
+
+    #[command(subcommand)]
+    #[command(visible_alias = "e")]
+    Export(export::Command),
+
+    #[command(subcommand)]
+    #[command(visible_alias = "i")]
+    Import(import::Command),
+
     #[command(subcommand)]
     #[command(visible_alias = "r")]
     Repo(repo::Command),
@@ -22,6 +48,12 @@ pub enum Command {
     #[command(subcommand)]
     #[command(visible_alias = "n")]
     Note(note::Command),
+
+    #[command(subcommand)]
+    Tag(tag::Command),
+
+    #[command(subcommand)]
+    Trash(trash::Command),
 }
 
 impl Command {
@@ -29,8 +61,85 @@ impl Command {
         match self {
             Self::Status(command) => command.run(env),
             Self::Tidy(command) => command.run(env),
+            Self::Review(command) => command.run(env),
+            Self::Backup(command) => command.run(env),
+            Self::Export(command) => command.run(env),
+            Self::Import(command) => command.run(env),
             Self::Repo(command) => command.run(env),
             Self::Note(command) => command.run(env),
+            Self::Tag(command) => command.run(env),
+            Self::Trash(command) => command.run(env),
+        }
+    }
+}
+
+/// How many matches of an ambiguous identifier are listed at most.
+const MAX_LISTED_MATCHES: usize = 10;
+
+/// Resolve a note id given on the command line, or a prefix of one, see
+/// [`gdn::ids::matching`].
+///
+/// If it doesn't refer to exactly one of the ids, the reason is printed
+/// instead.
+pub fn resolve_note(ids: impl IntoIterator<Item = NoteId>, identifier: &str) -> Option<NoteId> {
+    let matches = gdn::ids::matching(ids, identifier);
+    match matches[..] {
+        [id] => return Some(id),
+        [] => println!("No note found for identifier {identifier}."),
+        _ => {
+            println!("Identifier {identifier} is ambiguous, it could refer to:");
+            for id in matches.iter().take(MAX_LISTED_MATCHES) {
+                println!("- {id}");
+            }
+            if matches.len() > MAX_LISTED_MATCHES {
+                println!("- and {} more", matches.len() - MAX_LISTED_MATCHES);
+            }
+        }
+    }
+    None
+}
+
+/// Shorten an id to a prefix computed with [`gdn::ids::unique_prefix_len`].
+pub fn abbreviate(id: impl Display, len: usize) -> String {
+    let mut id = id.to_string();
+    id.truncate(len);
+    id
+}
+
+fn resolve_repo_candidates(
+    state: &State,
+    identifier: &str,
+    candidates: Vec<RepoId>,
+) -> Option<RepoId> {
+    match candidates[..] {
+        [id] => return Some(id),
+        [] => println!("No repo found for identifier {identifier}."),
+        _ => {
+            println!("Identifier {identifier} is ambiguous, it could refer to:");
+            for id in candidates {
+                println!("- {} ({id})", state.repos[&id]);
+            }
         }
     }
+    None
+}
+
+/// Resolve a repo identifier given on the command line.
+///
+/// If it doesn't refer to exactly one repo, the reason is printed instead.
+pub fn resolve_repo(state: &State, identifier: &str) -> Option<RepoId> {
+    resolve_repo_candidates(state, identifier, state.repo_candidates(identifier))
+}
+
+/// Like [`resolve_repo`], but only accepts a full repo id or name.
+///
+/// Used by commands that destroy data, where a typo shouldn't silently pick a
+/// different repo.
+pub fn resolve_repo_exact(state: &State, identifier: &str) -> Option<RepoId> {
+    let candidates = state.exact_repo_candidates(identifier);
+    if candidates.is_empty() && !state.repo_candidates(identifier).is_empty() {
+        println!("No repo has the id or name {identifier}, abbreviations are not allowed here.");
+        return None;
+    }
+    resolve_repo_candidates(state, identifier, candidates)
 }
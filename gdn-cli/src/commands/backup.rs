@@ -0,0 +1,25 @@
+mod create;
+mod restore;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Back up and restore the data dir.
+#[derive(Debug, Parser)]
+pub enum Command {
+    #[command(visible_alias = "c")]
+    Create(create::Command),
+
+    #[command(visible_alias = "r")]
+    Restore(restore::Command),
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        match self {
+            Self::Create(command) => command.run(env),
+            Self::Restore(command) => command.run(env),
+        }
+    }
+}
@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Create a backup of the data dir.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// Where to write the backup archive.
+    ///
+    /// Defaults to a new file in the data dir's backups directory.
+    #[arg(long)]
+    to: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_exclusive(env.data_dir.clone())?;
+        let target = match self.to {
+            Some(to) => to,
+            None => gdn::data::backup_file(&data)?,
+        };
+        gdn::data::create_backup(&data, &target)?;
+        println!("Created backup at {}.", target.display());
+        Ok(())
+    }
+}
@@ -0,0 +1,31 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Restore a backup into a fresh data dir.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The backup archive to restore.
+    backup: PathBuf,
+
+    /// The data dir to restore into. Must be empty or not exist yet.
+    ///
+    /// Defaults to the usual data dir.
+    #[arg(long)]
+    to: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let target = self.to.unwrap_or_else(|| env.data_dir.clone());
+        let version = gdn::data::restore_backup(&self.backup, target.clone())?;
+        println!(
+            "Restored backup {} to {} (data dir version {version}).",
+            self.backup.display(),
+            target.display()
+        );
+        Ok(())
+    }
+}
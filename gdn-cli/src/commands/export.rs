@@ -0,0 +1,39 @@
+mod anki;
+mod json;
+mod markdown;
+mod opml;
+mod org;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Export notes to other formats.
+#[derive(Debug, Parser)]
+pub enum Command {
+    #[command(visible_alias = "a")]
+    Anki(anki::Command),
+
+    #[command(visible_alias = "j")]
+    Json(json::Command),
+
+    #[command(visible_alias = "md")]
+    Markdown(markdown::Command),
+
+    #[command(visible_alias = "o")]
+    Opml(opml::Command),
+
+    Org(org::Command),
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        match self {
+            Self::Anki(command) => command.run(env),
+            Self::Json(command) => command.run(env),
+            Self::Markdown(command) => command.run(env),
+            Self::Opml(command) => command.run(env),
+            Self::Org(command) => command.run(env),
+        }
+    }
+}
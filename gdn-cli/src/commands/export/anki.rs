@@ -0,0 +1,47 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::Environment;
+
+/// Export flashcards from the selected repository as an Anki deck.
+///
+/// Notes of the form `question::answer` and notes ending with `?` that have
+/// children become cards. The resulting TSV file can be imported into Anki
+/// repeatedly, updating the existing cards.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The file to write to. Defaults to stdout.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// The name of the Anki deck. Defaults to the repository name.
+    #[arg(long, short)]
+    deck: Option<String>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let deck = self
+            .deck
+            .or_else(|| state.repos.get(&selected).cloned())
+            .unwrap_or_else(|| gdn::PROPER_NAME.to_string());
+        let tsv = gdn::export::anki::export(&store, &deck);
+
+        match self.output {
+            Some(path) => fs::write(path, tsv)?,
+            None => print!("{tsv}"),
+        }
+
+        Ok(())
+    }
+}
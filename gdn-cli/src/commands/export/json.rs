@@ -0,0 +1,54 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+};
+
+use clap::Parser;
+use gdn::export::json::RepoInfo;
+
+use crate::Environment;
+
+/// Export all notes of the selected repository as JSON.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The file to write to. Defaults to stdout.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// Write one JSON value per line instead of a single document.
+    #[arg(long, short)]
+    lines: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let repo = env.load_repo(&data, selected)?;
+        let info = RepoInfo {
+            id: selected,
+            name: state.repos.get(&selected).cloned().unwrap_or_default(),
+        };
+
+        let mut writer: Box<dyn Write> = match self.output {
+            Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+            None => Box::new(io::stdout().lock()),
+        };
+
+        let load_attachment = |hash: &str| gdn::data::load_attachment(&data, selected, hash);
+        if self.lines {
+            gdn::export::json::export_lines(&mut writer, &repo, Some(info), load_attachment)?;
+        } else {
+            gdn::export::json::export(&mut writer, &repo, Some(info), load_attachment)?;
+            writeln!(writer)?;
+        }
+        writer.flush()?;
+
+        Ok(())
+    }
+}
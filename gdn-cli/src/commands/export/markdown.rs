@@ -0,0 +1,30 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::Environment;
+
+/// Export the selected repository as a directory of Markdown files.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The directory to export to. Must be empty or not exist yet.
+    dir: PathBuf,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let count = gdn::export::markdown::export(&store, &self.dir)?;
+        println!("Exported {count} notes to {}.", self.dir.display());
+
+        Ok(())
+    }
+}
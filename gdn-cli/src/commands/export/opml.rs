@@ -0,0 +1,48 @@
+use std::{fs, path::PathBuf};
+
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Export notes of the selected repository as an OPML outline.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The note to export along with its descendants.
+    ///
+    /// If omitted, all notes are exported.
+    root: Option<String>,
+
+    /// The file to write to. Defaults to stdout.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let opml = match &self.root {
+            Some(root) => {
+                let Some(root) = resolve_note(store.ids(), root) else {
+                    return Ok(());
+                };
+                gdn::export::opml::export(&store, root)?
+            }
+            None => gdn::export::opml::export_all(&store),
+        };
+
+        match self.output {
+            Some(path) => fs::write(path, opml)?,
+            None => print!("{opml}"),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+mod json;
+mod markdown;
+mod opml;
+mod org;
+
+use std::collections::{HashMap, HashSet};
+
+use clap::Parser;
+use gdn::{ids::NoteId, repo::Note};
+
+use crate::Environment;
+
+/// Import notes from other formats.
+#[derive(Debug, Parser)]
+pub enum Command {
+    #[command(visible_alias = "j")]
+    Json(json::Command),
+
+    #[command(visible_alias = "md")]
+    Markdown(markdown::Command),
+
+    #[command(visible_alias = "o")]
+    Opml(opml::Command),
+
+    Org(org::Command),
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        match self {
+            Self::Json(command) => command.run(env),
+            Self::Markdown(command) => command.run(env),
+            Self::Opml(command) => command.run(env),
+            Self::Org(command) => command.run(env),
+        }
+    }
+}
+
+/// Print the notes reachable from `root` as an indented outline.
+///
+/// Notes that appear more than once are only expanded the first time.
+pub fn print_outline(notes: &[Note], root: NoteId) {
+    let notes = notes
+        .iter()
+        .map(|note| (note.id, note))
+        .collect::<HashMap<_, _>>();
+
+    let mut printed = HashSet::new();
+    let mut stack = vec![(0, root)];
+    while let Some((depth, id)) = stack.pop() {
+        let indent = "  ".repeat(depth);
+        let Some(note) = notes.get(&id) else {
+            println!("{indent}- {id} (existing note)");
+            continue;
+        };
+
+        let text = note.text.lines().next().unwrap_or_default();
+        if !printed.insert(id) {
+            println!("{indent}- {text} (see above)");
+            continue;
+        }
+
+        println!("{indent}- {text}");
+        for child in note.children.iter().rev() {
+            stack.push((depth + 1, *child));
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    fs,
+    io::{self, Read},
+    path::PathBuf,
+};
+
+use anyhow::Context;
+use clap::Parser;
+
+use crate::Environment;
+
+/// Import a JSON dump into the selected repository.
+///
+/// Notes whose id already exists in the repository replace the existing note,
+/// all other notes are added. Reviews and attachments are imported along with
+/// their notes and trashed notes are put in the trash.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The dump to import, or `-` to read from stdin.
+    file: PathBuf,
+
+    /// Only show what would be imported without saving anything.
+    #[arg(long, short = 'n')]
+    dry_run: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let json = if self.file.as_os_str() == "-" {
+            let mut json = String::new();
+            io::stdin().read_to_string(&mut json)?;
+            json
+        } else {
+            fs::read_to_string(&self.file)
+                .with_context(|| format!("failed to read {}", self.file.display()))?
+        };
+        let dump = gdn::import::json::import(&json)?;
+
+        if let Some(repo) = &dump.header.repo {
+            println!("Dump of repo {} ({})", repo.name, repo.id);
+        }
+
+        if self.dry_run {
+            println!("Would import {} notes.", dump.notes.len());
+            return Ok(());
+        }
+
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut repo = env.load_repo(&data, selected)?;
+
+        let summary = gdn::import::json::merge(&mut repo, dump.notes, |content| {
+            gdn::data::save_attachment(&data, selected, content)
+        })?;
+
+        let oid = gdn::data::save_repo(&data, selected, repo)?;
+        println!(
+            "Added {} and updated {} notes, put {} notes in the trash ({oid}).",
+            summary.added, summary.updated, summary.trashed
+        );
+
+        Ok(())
+    }
+}
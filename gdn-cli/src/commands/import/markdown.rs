@@ -0,0 +1,67 @@
+use std::{collections::HashSet, path::PathBuf};
+
+use clap::Parser;
+use gdn::import::markdown::Import;
+
+use crate::{Environment, commands::import::print_outline};
+
+/// Import a directory of Markdown files into the selected repository.
+///
+/// Works with Obsidian vaults. Files, headings, list items and paragraphs
+/// become notes, and `[[wikilinks]]` become references to child notes.
+#[derive(Debug, Parser)]
+pub struct Command {
+    dir: PathBuf,
+
+    /// Only show what would be imported without saving anything.
+    #[arg(long, short = 'n')]
+    dry_run: bool,
+}
+
+fn print_unresolved(import: &Import) {
+    for link in &import.unresolved {
+        println!(
+            "Unresolved link [[{}]] in {}",
+            link.target,
+            link.file.display()
+        );
+    }
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        if self.dry_run {
+            // Without a repo, fresh ids only need to be unique in the import.
+            let import = gdn::import::markdown::import(&self.dir, &HashSet::new())?;
+            print_unresolved(&import);
+            print_outline(&import.notes, import.root);
+            println!(
+                "Would import {} notes from {} files.",
+                import.notes.len(),
+                import.files
+            );
+            return Ok(());
+        }
+
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut repo = env.load_repo(&data, selected)?;
+        let import = gdn::import::markdown::import(&self.dir, &repo.ids())?;
+        print_unresolved(&import);
+
+        let count = import.notes.len();
+        repo.notes.extend(import.notes);
+
+        let oid = gdn::data::save_repo(&data, selected, repo)?;
+        println!(
+            "Imported {count} notes from {} files ({oid}).",
+            import.files
+        );
+
+        Ok(())
+    }
+}
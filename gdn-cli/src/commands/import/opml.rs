@@ -0,0 +1,54 @@
+use std::{collections::HashSet, fs, path::PathBuf};
+
+use anyhow::Context;
+use clap::Parser;
+
+use crate::{Environment, commands::import::print_outline};
+
+/// Import an OPML outline into the selected repository.
+///
+/// Outlines carrying note ids, like those created by `export opml`, update the
+/// existing notes with these ids.
+#[derive(Debug, Parser)]
+pub struct Command {
+    file: PathBuf,
+
+    /// Only show what would be imported without saving anything.
+    #[arg(long, short = 'n')]
+    dry_run: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let opml = fs::read_to_string(&self.file)
+            .with_context(|| format!("failed to read {}", self.file.display()))?;
+        if self.dry_run {
+            // Without a repo, fresh ids only need to be unique in the import.
+            let import = gdn::import::opml::import(&opml, &HashSet::new())?;
+            for root in &import.roots {
+                print_outline(&import.notes, *root);
+            }
+            println!("Would import {} notes.", import.notes.len());
+            return Ok(());
+        }
+
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut repo = env.load_repo(&data, selected)?;
+        let import = gdn::import::opml::import(&opml, &repo.ids())?;
+
+        let summary = gdn::import::merge(&mut repo, import.notes);
+
+        let oid = gdn::data::save_repo(&data, selected, repo)?;
+        println!(
+            "Added {} and updated {} notes ({oid}).",
+            summary.added, summary.updated
+        );
+
+        Ok(())
+    }
+}
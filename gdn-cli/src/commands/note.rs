@@ -1,5 +1,14 @@
 mod add;
+mod attach;
+mod dedupe;
+mod delete;
+mod detach;
+mod duplicate;
+mod extract;
+mod links;
 mod list;
+mod property;
+mod transfer;
 
 use clap::Parser;
 
@@ -12,6 +21,17 @@ pub enum Command {
     List(list::Command),
     #[command(visible_alias = "a")]
     Add(add::Command),
+    Attach(attach::Command),
+    Dedupe(dedupe::Command),
+    #[command(visible_alias = "d")]
+    Delete(delete::Command),
+    Detach(detach::Command),
+    Duplicate(duplicate::Command),
+    Extract(extract::Command),
+    Links(links::Command),
+    #[command(visible_alias = "p")]
+    Property(property::Command),
+    Transfer(transfer::Command),
 }
 
 impl Command {
@@ -19,6 +39,15 @@ impl Command {
         match self {
             Self::List(command) => command.run(env),
             Self::Add(command) => command.run(env),
+            Self::Attach(command) => command.run(env),
+            Self::Dedupe(command) => command.run(env),
+            Self::Delete(command) => command.run(env),
+            Self::Detach(command) => command.run(env),
+            Self::Duplicate(command) => command.run(env),
+            Self::Extract(command) => command.run(env),
+            Self::Links(command) => command.run(env),
+            Self::Property(command) => command.run(env),
+            Self::Transfer(command) => command.run(env),
         }
     }
 }
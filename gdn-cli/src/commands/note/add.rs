@@ -1,6 +1,5 @@
 use clap::Parser;
-use gdn::ids::NoteId;
-use gdn::repo::Note;
+use gdn::store::Store;
 
 use crate::Environment;
 
@@ -18,15 +17,11 @@ impl Command {
             println!("No repo selected");
             return Ok(());
         };
-        let mut repo = gdn::data::load_repo(&data, selected)?;
+        let mut store = Store::load(env.load_repo(&data, selected)?);
 
-        repo.notes.push(Note {
-            id: NoteId::new(),
-            text: self.text,
-            children: vec![],
-        });
+        store.create(self.text);
 
-        let oid = gdn::data::save_repo(&data, selected, repo)?;
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
         println!("Note added ({oid}).");
 
         Ok(())
@@ -0,0 +1,64 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, anyhow};
+use clap::Parser;
+use gdn::{repo::Attachment, store::Store};
+
+use crate::{Environment, commands::resolve_note};
+
+/// Attach a file to a note.
+///
+/// An existing attachment with the same name is replaced.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    file: PathBuf,
+
+    /// The name of the attachment. Defaults to the file name.
+    #[arg(long, short)]
+    name: Option<String>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let name = match self.name {
+            Some(name) => name,
+            None => self
+                .file
+                .file_name()
+                .and_then(|it| it.to_str())
+                .ok_or_else(|| anyhow!("invalid file name {}", self.file.display()))?
+                .to_string(),
+        };
+        let content = fs::read(&self.file)
+            .with_context(|| format!("failed to read {}", self.file.display()))?;
+
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let hash = gdn::data::save_attachment(&data, selected, &content)?;
+        let attachment = Attachment {
+            name: name.clone(),
+            hash,
+            size: content.len() as u64,
+        };
+        if store.attach(id, attachment).is_none() {
+            println!("No changes.");
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Attached {name} ({oid}).");
+
+        Ok(())
+    }
+}
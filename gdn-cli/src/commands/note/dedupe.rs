@@ -0,0 +1,85 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::Environment;
+
+fn parse_threshold(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(threshold) if threshold > 0.0 && threshold <= 1.0 => Ok(threshold),
+        _ => Err(format!("invalid threshold {s:?}, must be in (0, 1]")),
+    }
+}
+
+/// Find notes with the same or similar texts in the selected repository.
+///
+/// Texts are compared ignoring case and whitespace. Similar notes are grouped
+/// together, and each group can be merged into its oldest note. Merging moves
+/// the other notes to the trash and replaces them with the oldest note
+/// everywhere, keeping their children, tags and links.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// How similar texts must be, from 0 to 1.
+    #[arg(long, short, default_value_t = 0.8, value_parser = parse_threshold)]
+    threshold: f64,
+
+    /// Only find notes with the same text.
+    #[arg(long, short)]
+    exact: bool,
+
+    /// Merge each group into its oldest note.
+    #[arg(long, short)]
+    merge: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+
+        let threshold = if self.exact { 1.0 } else { self.threshold };
+        let groups = gdn::duplicates::find(&store, threshold);
+        if groups.is_empty() {
+            println!("No duplicates");
+            return Ok(());
+        }
+
+        for group in &groups {
+            let kind = if group.exact {
+                "Same text"
+            } else {
+                "Similar text"
+            };
+            println!("{kind}:");
+            for id in &group.ids {
+                println!("- {id}: {}", store.get(*id).unwrap().text);
+            }
+        }
+
+        if !self.merge {
+            return Ok(());
+        }
+
+        let mut merged = 0;
+        for group in &groups {
+            let keep = group.ids[0];
+            for remove in &group.ids[1..] {
+                if store.merge_notes(keep, *remove).is_some() {
+                    merged += 1;
+                }
+            }
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!(
+            "Merged {merged} duplicates into {} notes ({oid}).",
+            groups.len()
+        );
+
+        Ok(())
+    }
+}
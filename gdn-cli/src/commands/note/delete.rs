@@ -0,0 +1,47 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Delete a note from the selected repository.
+///
+/// The note is removed from the children of its parents and moved to the
+/// trash. Its own children are kept unless the whole subtree is deleted.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    /// Also delete all descendants that can't be reached from elsewhere.
+    #[arg(long, short)]
+    subtree: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let deleted = if self.subtree {
+            store.delete_subtree(id).len()
+        } else {
+            usize::from(store.delete(id).is_some())
+        };
+        if deleted == 0 {
+            println!("No notes deleted");
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Deleted {deleted} notes ({oid}).");
+
+        Ok(())
+    }
+}
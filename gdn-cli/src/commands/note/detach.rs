@@ -0,0 +1,37 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Remove an attachment from a note.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    name: String,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        if store.detach(id, &self.name).is_none() {
+            println!("No attachment {} on note {id}", self.name);
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Detached {} ({oid}).", self.name);
+
+        Ok(())
+    }
+}
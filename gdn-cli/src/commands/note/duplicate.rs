@@ -0,0 +1,37 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Copy a note and all of its descendants within the selected repository.
+///
+/// The copies get new ids. The copy of the note itself has no parents.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let Some(copy) = store.duplicate_subtree(id) else {
+            println!("No note with id {id}");
+            return Ok(());
+        };
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Duplicated {} as {copy} ({oid}).", id);
+
+        Ok(())
+    }
+}
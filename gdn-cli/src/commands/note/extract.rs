@@ -0,0 +1,58 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::bail;
+use clap::Parser;
+use gdn::store::LazyStore;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Save an attachment of a note to a file.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    name: String,
+
+    /// The file to write to. Defaults to the attachment's name in the current
+    /// directory.
+    #[arg(long, short)]
+    output: Option<PathBuf>,
+
+    /// Overwrite the file if it already exists.
+    #[arg(long, short)]
+    force: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = LazyStore::load(env.load_repo_lazy(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let Some(attachment) = store.attachment(id, &self.name)? else {
+            println!("No attachment {} on note {id}", self.name);
+            return Ok(());
+        };
+        let content = gdn::data::load_attachment(&data, selected, &attachment.hash)?;
+
+        // Attachment names come from the repo and may contain path separators.
+        let output = match self.output {
+            Some(output) => output,
+            None => PathBuf::from(attachment.name.replace(['/', '\\'], "_")),
+        };
+        if output.exists() && !self.force {
+            bail!("{} already exists", output.display());
+        }
+        fs::write(&output, content)?;
+        println!("Extracted {} to {}.", self.name, output.display());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use clap::Parser;
+use gdn::{ids::NoteId, store::Store};
+
+use crate::{Environment, commands::resolve_note};
+
+/// Show the inline links of a note.
+///
+/// Links have the form `[[<id>]]` or `[[<id>|<label>]]`. Without a note id,
+/// all links to notes that don't exist are shown instead.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: Option<String>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let Some(id) = &self.id else {
+            let dangling = store.dangling_links();
+            if dangling.is_empty() {
+                println!("No dangling links");
+            }
+            for (source, target) in dangling {
+                println!("{source} -> {target}");
+            }
+            return Ok(());
+        };
+
+        let Some(id) = resolve_note(store.ids(), id) else {
+            return Ok(());
+        };
+        let note = store.get(id).unwrap();
+
+        let describe = |id: NoteId| match store.get(id) {
+            Some(note) => format!("{id}: {}", note.text.lines().next().unwrap_or_default()),
+            None => format!("{id} (missing)"),
+        };
+
+        println!("Links: {}", note.links.len());
+        for link in &note.links {
+            println!("- {}", describe(*link));
+        }
+
+        let mut backlinks = note.backlinks.into_iter().collect::<Vec<_>>();
+        backlinks.sort_unstable();
+        println!("Backlinks: {}", backlinks.len());
+        for backlink in backlinks {
+            println!("- {}", describe(backlink));
+        }
+
+        Ok(())
+    }
+}
@@ -1,10 +1,22 @@
 use clap::Parser;
+use gdn::store::Store;
 
-use crate::Environment;
+use crate::{
+    Environment,
+    commands::{abbreviate, tag::parse_tag},
+};
 
 /// List all notes in the selected repository.
 #[derive(Debug, Parser)]
-pub struct Command {}
+pub struct Command {
+    /// Only list notes with this tag.
+    #[arg(long, short, value_parser = parse_tag)]
+    tag: Option<String>,
+
+    /// Show full ids instead of the shortest prefixes that tell them apart.
+    #[arg(long)]
+    no_abbrev: bool,
+}
 
 impl Command {
     pub fn run(self, env: &Environment) -> anyhow::Result<()> {
@@ -14,25 +26,42 @@ impl Command {
             println!("No repo selected");
             return Ok(());
         };
-        let mut repo = gdn::data::load_repo(&data, selected)?;
-        repo.notes.sort_unstable_by_key(|it| it.id);
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        // Computed before filtering, so prefixes refer to a single note in the
+        // whole repo.
+        let len = if self.no_abbrev {
+            usize::MAX
+        } else {
+            gdn::ids::unique_prefix_len(store.ids())
+        };
+
+        let ids = match &self.tag {
+            Some(tag) => store.notes_by_tag(tag),
+            None => {
+                let mut ids = store.ids().collect::<Vec<_>>();
+                ids.sort_unstable();
+                ids
+            }
+        };
 
-        if repo.notes.is_empty() {
+        if ids.is_empty() {
             println!("No notes");
             return Ok(());
         }
 
-        for note in repo.notes {
+        for note in ids.into_iter().filter_map(|id| store.get(id)) {
+            let id = abbreviate(note.id, len);
             if note.children.is_empty() {
-                println!("{}: {}", note.id, note.text);
+                println!("{id}: {}", note.text);
             } else {
                 let children = note
                     .children
                     .iter()
-                    .map(|it| it.to_string())
+                    .map(|it| abbreviate(it, len))
                     .collect::<Vec<_>>()
                     .join(", ");
-                println!("{}: {} [{children}]", note.id, note.text);
+                println!("{id}: {} [{children}]", note.text);
             }
         }
 
@@ -0,0 +1,104 @@
+use clap::Parser;
+use gdn::store::{LazyStore, Store};
+
+use crate::{Environment, commands::resolve_note};
+
+/// Show or change the properties of a note.
+///
+/// Without a key, all metadata of the note is shown. With a key but without a
+/// value, only that property is shown.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    key: Option<String>,
+
+    /// The new value of the property.
+    #[arg(requires = "key", conflicts_with = "remove")]
+    value: Option<String>,
+
+    /// Remove the property.
+    #[arg(long, short, requires = "key")]
+    remove: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        if self.value.is_none() && !self.remove {
+            return self.show(env);
+        }
+
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let key = self.key.unwrap();
+        if store.set_property(id, key, self.value).is_none() {
+            println!("No changes.");
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Property updated ({oid}).");
+
+        Ok(())
+    }
+
+    fn show(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = LazyStore::load(env.load_repo_lazy(&data, selected)?);
+        let Some(id) = resolve_note(store.ids(), &self.id) else {
+            return Ok(());
+        };
+
+        let Some(note) = store.get(id)? else {
+            println!("No note with id {id}");
+            return Ok(());
+        };
+
+        if let Some(key) = self.key {
+            match note.meta.properties.get(&key) {
+                Some(value) => println!("{value}"),
+                None => println!("No property {key:?}"),
+            }
+            return Ok(());
+        }
+
+        let created = id.time_utc().strftime("%Y-%m-%d %H:%M:%S UTC");
+        println!("Created: {created}");
+        if let Some(modified) = note.meta.modified {
+            println!("Modified: {}", modified.strftime("%Y-%m-%d %H:%M:%S UTC"));
+        }
+        if let Some(device) = &note.meta.device {
+            println!("Device: {device}");
+        }
+        if !note.meta.attachments.is_empty() {
+            println!("Attachments:");
+            for attachment in &note.meta.attachments {
+                println!("- {} ({} bytes)", attachment.name, attachment.size);
+            }
+        }
+        if note.meta.properties.is_empty() {
+            println!("No properties");
+        } else {
+            println!("Properties:");
+            for (key, value) in &note.meta.properties {
+                println!("- {key}: {value}");
+            }
+        }
+
+        Ok(())
+    }
+}
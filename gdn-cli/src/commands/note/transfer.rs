@@ -0,0 +1,71 @@
+use clap::Parser;
+use gdn::data::Transfer;
+
+use crate::{
+    Environment,
+    commands::{resolve_note, resolve_repo_exact},
+};
+
+/// Copy a note and all of its descendants to a different repository.
+///
+/// Copies get new ids. When moving, the notes keep their ids and are deleted
+/// from the selected repository. Notes with descendants that can also be
+/// reached from elsewhere can only be copied.
+#[derive(Debug, Parser)]
+pub struct Command {
+    id: String,
+
+    /// The full id or name of the repository to transfer the notes to.
+    repo: String,
+
+    /// Move the notes instead of copying them.
+    #[arg(long, short)]
+    r#move: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let Some(target) = resolve_repo_exact(&state, &self.repo) else {
+            return Ok(());
+        };
+
+        let transfer = if self.r#move {
+            Transfer::Move
+        } else {
+            Transfer::Copy
+        };
+        let source = env.load_repo(&data, selected)?;
+        let Some(id) = resolve_note(source.notes.iter().map(|it| it.id), &self.id) else {
+            return Ok(());
+        };
+        let to_secret = env.repo_secret(&data, target)?;
+        let result = gdn::data::transfer_subtree(
+            &data,
+            id,
+            selected,
+            source,
+            target,
+            to_secret.as_ref(),
+            transfer,
+        )?;
+
+        println!(
+            "Transferred {} notes to {} as {} ({}).",
+            result.notes, self.repo, result.root, result.target
+        );
+        if let Some(source) = result.source {
+            println!(
+                "Deleted {} of them from the selected repo ({source}).",
+                result.removed
+            );
+        }
+
+        Ok(())
+    }
+}
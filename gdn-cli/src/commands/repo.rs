@@ -1,6 +1,9 @@
 mod add;
+mod decrypt;
+mod encrypt;
 mod info;
 mod list;
+mod rekey;
 mod remove;
 mod rename;
 mod select;
@@ -12,6 +15,9 @@ use crate::Environment;
 pub use self::list::print_repo_list;
 
 /// Perform repo operations.
+///
+/// Repositories are identified by their id, their name, or a prefix of either
+/// that only matches a single repository.
 #[derive(Debug, Parser)]
 pub enum Command {
     #[command(visible_alias = "l")]
@@ -31,6 +37,12 @@ pub enum Command {
 
     #[command(visible_alias = "r")]
     Remove(remove::Command),
+
+    Encrypt(encrypt::Command),
+
+    Rekey(rekey::Command),
+
+    Decrypt(decrypt::Command),
 }
 
 impl Command {
@@ -42,6 +54,9 @@ impl Command {
             Self::Add(command) => command.run(env),
             Self::Rename(command) => command.run(env),
             Self::Remove(command) => command.run(env),
+            Self::Encrypt(command) => command.run(env),
+            Self::Rekey(command) => command.run(env),
+            Self::Decrypt(command) => command.run(env),
         }
     }
 }
@@ -3,6 +3,8 @@ use clap::Parser;
 use crate::Environment;
 
 /// Add a new repository.
+///
+/// Its name must not be used by any other repository.
 #[derive(Debug, Parser)]
 pub struct Command {
     name: String,
@@ -11,6 +13,11 @@ pub struct Command {
 impl Command {
     pub fn run(self, env: &Environment) -> anyhow::Result<()> {
         let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        if let Some(other) = state.repo_by_name(&self.name) {
+            println!("Repo {other} is already called {}.", self.name);
+            return Ok(());
+        }
         let id = gdn::data::add_repo(&data, self.name.clone())?;
         println!("Added repo {} ({id}).", self.name);
         Ok(())
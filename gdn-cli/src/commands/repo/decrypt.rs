@@ -0,0 +1,35 @@
+use clap::Parser;
+
+use crate::{Environment, commands::resolve_repo_exact};
+
+/// Decrypt an encrypted repository.
+///
+/// From now on, notes and reviews are stored unencrypted.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The full id or name of the repository.
+    repo: String,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(id) = resolve_repo_exact(&state, &self.repo) else {
+            return Ok(());
+        };
+
+        if gdn::data::load_repo_scheme(&data, id)?.is_none() {
+            println!("Repo is not encrypted.");
+            return Ok(());
+        }
+
+        let mut repo = env.load_repo(&data, id)?;
+        repo.encryption = None;
+
+        let oid = gdn::data::save_repo(&data, id, repo)?;
+        println!("Decrypted repo {} ({oid}).", self.repo);
+
+        Ok(())
+    }
+}
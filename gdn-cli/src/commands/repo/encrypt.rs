@@ -0,0 +1,80 @@
+use std::path::PathBuf;
+
+use anyhow::{Context, bail};
+use clap::Parser;
+use gdn::crypto::{Encryption, Scheme, Secret};
+
+use crate::{Environment, commands::resolve_repo_exact};
+
+/// Ask for a new passphrase twice, or read a new keyfile.
+pub fn new_secret(keyfile: Option<PathBuf>) -> anyhow::Result<Secret> {
+    if let Some(keyfile) = keyfile {
+        return Secret::from_keyfile(&keyfile);
+    }
+
+    let passphrase =
+        rpassword::prompt_password("New passphrase: ").context("failed to read passphrase")?;
+    if passphrase.is_empty() {
+        bail!("passphrase must not be empty");
+    }
+    let repeated = rpassword::prompt_password("Repeat new passphrase: ")
+        .context("failed to read passphrase")?;
+    if repeated != passphrase {
+        bail!("passphrases don't match");
+    }
+    Ok(Secret::from_passphrase(passphrase))
+}
+
+/// Encrypt a repository.
+///
+/// From now on, notes and reviews are stored encrypted with a key derived from
+/// a passphrase or keyfile. The tree structure, which reveals the ids of notes
+/// and when they were created, is not encrypted. Previous commits are not
+/// modified and still contain unencrypted notes.
+///
+/// Attachments can't be encrypted, so repositories with attachments can't be
+/// encrypted either.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The full id or name of the repository.
+    repo: String,
+
+    /// Use the content of this file instead of a passphrase.
+    #[arg(long)]
+    new_keyfile: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(id) = resolve_repo_exact(&state, &self.repo) else {
+            return Ok(());
+        };
+
+        if gdn::data::load_repo_scheme(&data, id)?.is_some() {
+            println!("Repo is already encrypted, use `repo rekey` to change the key.");
+            return Ok(());
+        }
+
+        let mut repo = env.load_repo(&data, id)?;
+        let attachments = repo
+            .notes
+            .iter()
+            .chain(repo.trash.iter().map(|it| &it.note))
+            .any(|note| !note.meta.attachments.is_empty());
+        if attachments {
+            bail!("repo has attachments, which can't be encrypted");
+        }
+
+        let (scheme, key) = Scheme::new(&new_secret(self.new_keyfile)?)?;
+        repo.encryption = Some(Encryption { scheme, key });
+
+        let oid = gdn::data::save_repo(&data, id, repo)?;
+        println!("Encrypted repo {} ({oid}).", self.repo);
+        println!("Previous commits are still readable without the key.");
+        println!("The ids of notes and when they were created are not encrypted.");
+
+        Ok(())
+    }
+}
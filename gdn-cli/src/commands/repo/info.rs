@@ -1,7 +1,7 @@
 use clap::Parser;
 use gdn::data::REPO_VERSION;
 
-use crate::Environment;
+use crate::{Environment, commands::resolve_repo};
 
 /// Show info about a repository.
 #[derive(Debug, Parser)]
@@ -14,15 +14,16 @@ impl Command {
         let data = gdn::data::open(env.data_dir.clone())?;
         let state = gdn::data::load_state(&data)?;
 
-        let Some(id) = state.resolve_repo_identifier(&self.repo) else {
-            println!("No repo found for identifier {}.", self.repo);
+        let Some(id) = resolve_repo(&state, &self.repo) else {
             return Ok(());
         };
 
         let version = gdn::data::load_repo_version(&data, id)?;
-        let repo = gdn::data::load_repo(&data, id)?;
+        let encrypted = gdn::data::load_repo_scheme(&data, id)?.is_some();
+        let repo = env.load_repo(&data, id)?;
 
         println!("Repo version: {version} (latest: {REPO_VERSION})",);
+        println!("Encrypted: {}", if encrypted { "yes" } else { "no" });
         println!("Number of notes: {}", repo.notes.len());
 
         Ok(())
@@ -1,28 +1,53 @@
 use clap::Parser;
-use gdn::data::State;
+use gdn::{data::State, ids::RepoId};
 
 use crate::Environment;
 
 /// List all repositories.
 #[derive(Debug, Parser)]
-pub struct Command {}
+pub struct Command {
+    /// Only print the names, one per line, for example for shell completion.
+    #[arg(long, short, conflicts_with = "ids")]
+    names: bool,
+
+    /// Only print the ids, one per line, for example for shell completion.
+    #[arg(long, short)]
+    ids: bool,
+}
 
 impl Command {
     pub fn run(self, env: &Environment) -> anyhow::Result<()> {
         let data = gdn::data::open(env.data_dir.clone())?;
         let state = gdn::data::load_state(&data)?;
+
+        if self.names || self.ids {
+            for (name, id) in sorted_repos(&state) {
+                if self.names {
+                    println!("{name}");
+                } else {
+                    println!("{id}");
+                }
+            }
+            return Ok(());
+        }
+
         print_repo_list(&state);
         Ok(())
     }
 }
 
-pub fn print_repo_list(state: &State) {
+fn sorted_repos(state: &State) -> Vec<(&String, RepoId)> {
     let mut repos = state
         .repos
         .iter()
         .map(|(id, name)| (name, *id))
         .collect::<Vec<_>>();
     repos.sort_unstable();
+    repos
+}
+
+pub fn print_repo_list(state: &State) {
+    let repos = sorted_repos(state);
 
     if repos.is_empty() {
         println!("No repos");
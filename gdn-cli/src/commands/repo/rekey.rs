@@ -0,0 +1,47 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use gdn::crypto::{Encryption, Scheme};
+
+use crate::{
+    Environment,
+    commands::{repo::encrypt::new_secret, resolve_repo_exact},
+};
+
+/// Change the passphrase or keyfile of an encrypted repository.
+///
+/// All notes and reviews are encrypted again with the new key in a single
+/// commit. Previous commits can still be decrypted with the old key.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The full id or name of the repository.
+    repo: String,
+
+    /// Use the content of this file instead of a new passphrase.
+    #[arg(long)]
+    new_keyfile: Option<PathBuf>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(id) = resolve_repo_exact(&state, &self.repo) else {
+            return Ok(());
+        };
+
+        if gdn::data::load_repo_scheme(&data, id)?.is_none() {
+            println!("Repo is not encrypted, use `repo encrypt` to encrypt it.");
+            return Ok(());
+        }
+
+        let mut repo = env.load_repo(&data, id)?;
+        let (scheme, key) = Scheme::new(&new_secret(self.new_keyfile)?)?;
+        repo.encryption = Some(Encryption { scheme, key });
+
+        let oid = gdn::data::save_repo(&data, id, repo)?;
+        println!("Changed key of repo {} ({oid}).", self.repo);
+
+        Ok(())
+    }
+}
@@ -1,10 +1,11 @@
 use clap::Parser;
 
-use crate::Environment;
+use crate::{Environment, commands::resolve_repo_exact};
 
 /// Remove an existing repository.
 #[derive(Debug, Parser)]
 pub struct Command {
+    /// The full id or name of the repository.
     repo: String,
 }
 
@@ -12,8 +13,7 @@ impl Command {
     pub fn run(self, env: &Environment) -> anyhow::Result<()> {
         let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
         let state = gdn::data::load_state(&data)?;
-        let Some(id) = state.resolve_repo_identifier(&self.repo) else {
-            println!("No repo found for identifier {}.", self.repo);
+        let Some(id) = resolve_repo_exact(&state, &self.repo) else {
             return Ok(());
         };
         gdn::data::remove_repo(&data, id)?;
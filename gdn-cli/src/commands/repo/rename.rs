@@ -1,10 +1,13 @@
 use clap::Parser;
 
-use crate::Environment;
+use crate::{Environment, commands::resolve_repo_exact};
 
 /// Rename an existing repository.
+///
+/// The new name must not be used by any other repository.
 #[derive(Debug, Parser)]
 pub struct Command {
+    /// The full id or name of the repository.
     repo: String,
     name: String,
 }
@@ -13,10 +16,15 @@ impl Command {
     pub fn run(self, env: &Environment) -> anyhow::Result<()> {
         let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
         let state = gdn::data::load_state(&data)?;
-        let Some(id) = state.resolve_repo_identifier(&self.repo) else {
-            println!("No repo found for identifier {}.", self.repo);
+        let Some(id) = resolve_repo_exact(&state, &self.repo) else {
             return Ok(());
         };
+        if let Some(other) = state.repo_by_name(&self.name)
+            && other != id
+        {
+            println!("Repo {other} is already called {}.", self.name);
+            return Ok(());
+        }
         gdn::data::rename_repo(&data, id, self.name.clone())?;
         println!("Renamed repo {} ({id}) to {}.", self.repo, self.name);
         Ok(())
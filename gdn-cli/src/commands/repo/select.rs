@@ -1,6 +1,6 @@
 use clap::Parser;
 
-use crate::Environment;
+use crate::{Environment, commands::resolve_repo};
 
 /// Select a repository.
 #[derive(Debug, Parser)]
@@ -19,8 +19,7 @@ impl Command {
             return Ok(());
         }
 
-        let Some(id) = state.resolve_repo_identifier(&self.repo) else {
-            println!("No repo found for identifier {}.", self.repo);
+        let Some(id) = resolve_repo(&state, &self.repo) else {
             return Ok(());
         };
 
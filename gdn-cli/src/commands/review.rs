@@ -0,0 +1,106 @@
+use std::io::{self, BufRead, Write};
+
+use clap::Parser;
+use gdn::{ids::NoteId, repo::Grade, store::Store};
+use jiff::Timestamp;
+
+use crate::Environment;
+
+fn prompt(
+    lines: &mut impl Iterator<Item = io::Result<String>>,
+    text: &str,
+) -> anyhow::Result<Option<String>> {
+    print!("{text}");
+    io::stdout().flush()?;
+    Ok(lines.next().transpose()?)
+}
+
+fn parse_grade(input: &str) -> Option<Grade> {
+    match input.trim() {
+        "1" => Some(Grade::Again),
+        "2" => Some(Grade::Hard),
+        "3" => Some(Grade::Good),
+        "4" => Some(Grade::Easy),
+        _ => None,
+    }
+}
+
+/// Review the flashcards of the selected repository that are due.
+///
+/// Cards are shown one after another. After recalling the answer, press enter
+/// to reveal it and grade how well you remembered it. Enter `q` to stop
+/// early. The grades are saved once the session ends and determine when each
+/// card is due again.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// How many cards that were never reviewed to include.
+    #[arg(long, default_value_t = 20)]
+    new: usize,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        // The repo is not locked during the session, which can take a while.
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+        data.unlock()?;
+
+        let cards = gdn::review::due_cards(&store, Timestamp::now(), self.new);
+        if cards.is_empty() {
+            println!("No cards due.");
+            return Ok(());
+        }
+
+        let mut results: Vec<(NoteId, Grade, Timestamp)> = vec![];
+        let mut lines = io::stdin().lock().lines();
+        'cards: for (i, card) in cards.iter().enumerate() {
+            println!();
+            println!("[{}/{}] {}", i + 1, cards.len(), card.front);
+            match prompt(&mut lines, "Press enter to show the answer...")? {
+                Some(input) if input.trim() != "q" => {}
+                _ => break,
+            }
+
+            println!("{}", card.back);
+            let grade = loop {
+                let Some(input) = prompt(&mut lines, "1 again, 2 hard, 3 good, 4 easy: ")? else {
+                    break 'cards;
+                };
+                if input.trim() == "q" {
+                    break 'cards;
+                }
+                if let Some(grade) = parse_grade(&input) {
+                    break grade;
+                }
+            };
+            results.push((card.id, grade, Timestamp::now()));
+        }
+
+        println!();
+        if results.is_empty() {
+            println!("No cards reviewed.");
+            return Ok(());
+        }
+
+        // Other changes may have happened during the session, so the repo is
+        // loaded again before recording the results.
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+        let mut reviewed = 0;
+        for (id, grade, time) in results {
+            if store.record_review(id, grade, time).is_some() {
+                reviewed += 1;
+            }
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Reviewed {reviewed} cards ({oid}).");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+mod list;
+mod merge;
+mod rename;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Perform tag operations.
+///
+/// Tags are words in a note's text starting with a `#`, like `#todo`.
+#[derive(Debug, Parser)]
+pub enum Command {
+    #[command(visible_alias = "l")]
+    List(list::Command),
+
+    #[command(visible_alias = "rn")]
+    Rename(rename::Command),
+
+    #[command(visible_alias = "m")]
+    Merge(merge::Command),
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        match self {
+            Self::List(command) => command.run(env),
+            Self::Rename(command) => command.run(env),
+            Self::Merge(command) => command.run(env),
+        }
+    }
+}
+
+/// Parse a tag given on the command line, with or without its leading `#`.
+pub fn parse_tag(s: &str) -> Result<String, String> {
+    let tag = s.strip_prefix('#').unwrap_or(s);
+    if !gdn::tags::is_valid(tag) {
+        return Err(format!("invalid tag {s:?}"));
+    }
+    Ok(tag.to_string())
+}
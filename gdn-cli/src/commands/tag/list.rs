@@ -0,0 +1,32 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::Environment;
+
+/// List all tags in the selected repository.
+#[derive(Debug, Parser)]
+pub struct Command {}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let tags = store.tags();
+        if tags.is_empty() {
+            println!("No tags");
+            return Ok(());
+        }
+
+        for tag in tags {
+            println!("#{tag} ({})", store.notes_by_tag(tag).len());
+        }
+
+        Ok(())
+    }
+}
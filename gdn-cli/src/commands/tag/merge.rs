@@ -0,0 +1,43 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::tag::parse_tag};
+
+/// Merge tags into a single tag in all notes of the selected repository.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// The tags to merge.
+    #[arg(required = true, value_parser = parse_tag)]
+    tags: Vec<String>,
+
+    /// The tag to merge into. It doesn't need to exist yet.
+    #[arg(long, short, value_parser = parse_tag)]
+    into: String,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+
+        let mut count = 0;
+        for tag in &self.tags {
+            count += store.rename_tag(tag, &self.into);
+        }
+
+        if count == 0 {
+            println!("No notes to change.");
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Merged tags into #{} in {count} notes ({oid}).", self.into);
+
+        Ok(())
+    }
+}
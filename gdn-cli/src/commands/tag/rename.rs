@@ -0,0 +1,48 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::tag::parse_tag};
+
+/// Rename a tag in all notes of the selected repository.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[arg(value_parser = parse_tag)]
+    tag: String,
+
+    #[arg(value_parser = parse_tag)]
+    name: String,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+
+        if store.notes_by_tag(&self.tag).is_empty() {
+            println!("No notes with tag #{}.", self.tag);
+            return Ok(());
+        }
+        if !store.notes_by_tag(&self.name).is_empty() {
+            println!(
+                "Tag #{} already exists, use `tag merge` to merge the tags.",
+                self.name
+            );
+            return Ok(());
+        }
+
+        let count = store.rename_tag(&self.tag, &self.name);
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!(
+            "Renamed tag #{} to #{} in {count} notes ({oid}).",
+            self.tag, self.name
+        );
+
+        Ok(())
+    }
+}
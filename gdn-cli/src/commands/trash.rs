@@ -0,0 +1,32 @@
+mod empty;
+mod list;
+mod restore;
+
+use clap::Parser;
+
+use crate::Environment;
+
+/// Perform trash operations.
+///
+/// Deleted notes are moved to the trash of their repository. They are purged
+/// automatically once they have been in the trash for 30 days.
+#[derive(Debug, Parser)]
+pub enum Command {
+    #[command(visible_alias = "l")]
+    List(list::Command),
+
+    #[command(visible_alias = "r")]
+    Restore(restore::Command),
+
+    Empty(empty::Command),
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        match self {
+            Self::List(command) => command.run(env),
+            Self::Restore(command) => command.run(env),
+            Self::Empty(command) => command.run(env),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use clap::Parser;
+use gdn::store::Store;
+use jiff::{SignedDuration, Timestamp};
+
+use crate::Environment;
+
+/// Permanently delete the notes in the trash of the selected repository.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// Only delete notes that have been in the trash for this many days.
+    #[arg(long, short)]
+    older_than: Option<u32>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+
+        let purged = match self.older_than {
+            None => store.empty_trash(),
+            Some(days) => {
+                let age = SignedDuration::from_hours(i64::from(days) * 24);
+                // If the cutoff is out of range, no note is that old.
+                match Timestamp::now().checked_sub(age) {
+                    Ok(cutoff) => store.purge_trash_before(cutoff),
+                    Err(_) => 0,
+                }
+            }
+        };
+        if purged == 0 {
+            println!("No notes to delete");
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Deleted {purged} notes permanently ({oid}).");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,44 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::abbreviate};
+
+/// List all notes in the trash of the selected repository.
+#[derive(Debug, Parser)]
+pub struct Command {
+    /// Show full ids instead of the shortest prefixes that tell them apart.
+    #[arg(long)]
+    no_abbrev: bool,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let store = Store::load(env.load_repo(&data, selected)?);
+
+        let trash = store.trash();
+        if trash.is_empty() {
+            println!("Trash is empty");
+            return Ok(());
+        }
+
+        let len = if self.no_abbrev {
+            usize::MAX
+        } else {
+            gdn::ids::unique_prefix_len(trash.iter().map(|(id, _)| *id))
+        };
+
+        for (id, trashed) in trash {
+            let id = abbreviate(id, len);
+            let deleted = trashed.deleted.strftime("%Y-%m-%d %H:%M:%S UTC");
+            println!("{id} (deleted {deleted}): {}", trashed.note.text);
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use clap::Parser;
+use gdn::store::Store;
+
+use crate::{Environment, commands::resolve_note};
+
+/// Restore notes from the trash of the selected repository.
+///
+/// Restored notes become children of their previous parents again, as long as
+/// these still exist.
+#[derive(Debug, Parser)]
+pub struct Command {
+    #[arg(required = true)]
+    ids: Vec<String>,
+}
+
+impl Command {
+    pub fn run(self, env: &Environment) -> anyhow::Result<()> {
+        let data = gdn::data::open_and_migrate(env.data_dir.clone())?;
+        let state = gdn::data::load_state(&data)?;
+        let Some(selected) = state.selected_repo else {
+            println!("No repo selected");
+            return Ok(());
+        };
+        let mut store = Store::load(env.load_repo(&data, selected)?);
+
+        let mut restored = 0;
+        for id in &self.ids {
+            let trash = store.trash().into_iter().map(|(id, _)| id);
+            let Some(id) = resolve_note(trash, id) else {
+                continue;
+            };
+            if store.restore(id).is_some() {
+                restored += 1;
+            } else {
+                println!("Note {id} already exists");
+            }
+        }
+        if restored == 0 {
+            return Ok(());
+        }
+
+        let oid = gdn::data::save_repo(&data, selected, store.save())?;
+        println!("Restored {restored} notes ({oid}).");
+
+        Ok(())
+    }
+}
@@ -1,6 +1,13 @@
-use std::path::PathBuf;
+use std::{cell::RefCell, env, path::PathBuf};
 
+use anyhow::Context;
 use clap::Parser;
+use gdn::{
+    crypto::Secret,
+    data::UnlockedDataDir,
+    ids::RepoId,
+    repo::{LazyRepo, Repo},
+};
 
 use crate::commands::Command;
 
@@ -13,12 +20,69 @@ struct Args {
     /// Path to the config file.
     #[arg(long, short)]
     config: Option<PathBuf>,
+    /// Keyfile to unlock encrypted repositories with.
+    ///
+    /// Without a keyfile, the passphrase is read from the GDN_PASSPHRASE
+    /// environment variable or asked for interactively.
+    #[arg(long, global = true)]
+    keyfile: Option<PathBuf>,
     #[command(subcommand)]
     cmd: Command,
 }
 
+/// Environment variable containing the passphrase for encrypted repos.
+const PASSPHRASE_VAR: &str = "GDN_PASSPHRASE";
+
 struct Environment {
     data_dir: PathBuf,
+    keyfile: Option<PathBuf>,
+    secret: RefCell<Option<Secret>>,
+}
+
+impl Environment {
+    /// The secret used to unlock encrypted repos.
+    ///
+    /// It is only determined once, so the user is asked at most once.
+    fn secret(&self) -> anyhow::Result<Secret> {
+        if let Some(secret) = &*self.secret.borrow() {
+            return Ok(secret.clone());
+        }
+
+        let secret = match &self.keyfile {
+            Some(keyfile) => Secret::from_keyfile(keyfile)?,
+            None => match env::var(PASSPHRASE_VAR) {
+                Ok(passphrase) => Secret::from_passphrase(passphrase),
+                Err(_) => {
+                    let passphrase = rpassword::prompt_password("Passphrase: ")
+                        .context("failed to read passphrase")?;
+                    Secret::from_passphrase(passphrase)
+                }
+            },
+        };
+
+        *self.secret.borrow_mut() = Some(secret.clone());
+        Ok(secret)
+    }
+
+    /// The secret needed to unlock a repo, if it is encrypted.
+    fn repo_secret(&self, data: &UnlockedDataDir, id: RepoId) -> anyhow::Result<Option<Secret>> {
+        match gdn::data::load_repo_scheme(data, id)? {
+            Some(_) => Ok(Some(self.secret()?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Load a repo, unlocking it first if it is encrypted.
+    fn load_repo(&self, data: &UnlockedDataDir, id: RepoId) -> anyhow::Result<Repo> {
+        let secret = self.repo_secret(data, id)?;
+        gdn::data::load_repo(data, id, secret.as_ref())
+    }
+
+    /// Like [`Self::load_repo`], but without reading every note up front.
+    fn load_repo_lazy(&self, data: &UnlockedDataDir, id: RepoId) -> anyhow::Result<LazyRepo> {
+        let secret = self.repo_secret(data, id)?;
+        gdn::data::load_repo_lazy(data, id, secret.as_ref())
+    }
 }
 
 fn run() -> anyhow::Result<()> {
@@ -26,6 +90,8 @@ fn run() -> anyhow::Result<()> {
 
     let env = Environment {
         data_dir: gdn::data::path()?,
+        keyfile: args.keyfile,
+        secret: RefCell::new(None),
     };
 
     args.cmd.run(&env)?;
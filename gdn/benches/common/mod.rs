@@ -0,0 +1,55 @@
+//! A large generated repo shared by the benchmarks.
+
+use std::{env, fs, path::Path, process};
+
+use gdn::{
+    ids::NoteId,
+    repo::{Metadata, Note, Repo},
+};
+
+pub const NOTES: u64 = 100_000;
+
+/// Notes are spread out over a bit more than three years, so the repo has
+/// many day directories.
+const SECONDS_BETWEEN_NOTES: u64 = 1000;
+
+const START: u64 = 1_700_000_000;
+
+pub fn note_id(i: u64) -> NoteId {
+    let secs = START + i * SECONDS_BETWEEN_NOTES;
+    format!("n{:016X}", secs << (3 * 8) | (i & 0xFFFFFF))
+        .parse()
+        .unwrap()
+}
+
+/// A repo where every note has up to three children, forming a tree.
+pub fn generate() -> Repo {
+    let notes = (0..NOTES)
+        .map(|i| Note {
+            id: note_id(i),
+            text: format!("Note number {i}"),
+            children: (1..=3)
+                .map(|j| i * 4 + j)
+                .filter(|j| *j < NOTES)
+                .map(note_id)
+                .collect(),
+            meta: Metadata::default(),
+        })
+        .collect();
+
+    Repo {
+        notes,
+        ..Repo::default()
+    }
+}
+
+/// Run a benchmark with a path for a repo in a temporary directory.
+pub fn with_temp_repo(
+    name: &str,
+    f: impl FnOnce(&Path) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let path = env::temp_dir().join(format!("gdn-bench-{name}-{}", process::id()));
+    let result = f(&path);
+    fs::remove_dir_all(&path)?;
+    result
+}
@@ -0,0 +1,84 @@
+//! Compare loading a large repo with a single thread to loading it with all
+//! available threads.
+//!
+//! Run with `cargo bench -p gdn --bench load`, optionally followed by `--` and
+//! the number of threads to compare against.
+
+// The benchmark only uses some of the crate's dependencies.
+#![allow(unused_crate_dependencies)]
+
+mod common;
+
+use std::{
+    env,
+    path::Path,
+    thread,
+    time::{Duration, Instant},
+};
+
+use gdn::{
+    crypto::{Encryption, Scheme, Secret},
+    repo::{self, Repo},
+};
+
+fn time(name: &str, f: impl FnOnce() -> anyhow::Result<Repo>) -> anyhow::Result<(Repo, Duration)> {
+    let start = Instant::now();
+    let result = f()?;
+    let elapsed = start.elapsed();
+    println!("{name:<24} {elapsed:>12.3?}");
+    Ok((result, elapsed))
+}
+
+fn compare(path: &Path, secret: Option<&Secret>, threads: usize) -> anyhow::Result<()> {
+    let (sequential, sequential_time) =
+        time("1 thread", || repo::load_with_threads(path, secret, 1))?;
+    let (parallel, parallel_time) = time(&format!("{threads} threads"), || {
+        repo::load_with_threads(path, secret, threads)
+    })?;
+
+    // The result must not depend on the number of threads.
+    assert_eq!(sequential.notes.len(), common::NOTES as usize);
+    assert_eq!(sequential.notes.len(), parallel.notes.len());
+    for (a, b) in sequential.notes.iter().zip(&parallel.notes) {
+        assert_eq!(a.id, b.id);
+        assert_eq!(a.text, b.text);
+        assert_eq!(a.children, b.children);
+    }
+
+    println!(
+        "speedup                  {:>11.1}x",
+        sequential_time.as_secs_f64() / parallel_time.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+fn run(path: &Path) -> anyhow::Result<()> {
+    // Cargo passes `--bench` to benchmarks without a harness.
+    let threads = match env::args().skip(1).find(|it| it != "--bench") {
+        Some(threads) => threads.parse()?,
+        None => thread::available_parallelism()?.get(),
+    };
+
+    repo::init(path)?;
+
+    println!("Unencrypted:");
+    repo::save(path, common::generate())?;
+    compare(path, None, threads)?;
+
+    println!("Encrypted:");
+    let secret = Secret::from_passphrase("benchmark".to_string());
+    let (scheme, key) = Scheme::new(&secret)?;
+    let repo = Repo {
+        encryption: Some(Encryption { scheme, key }),
+        ..common::generate()
+    };
+    repo::save(path, repo)?;
+    compare(path, Some(&secret), threads)?;
+
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    common::with_temp_repo("load", run)
+}
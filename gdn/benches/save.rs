@@ -0,0 +1,71 @@
+//! Compare saving a large repo from scratch to saving a single modified note.
+//!
+//! Run with `cargo bench -p gdn --bench save`.
+
+// The benchmark only uses some of the crate's dependencies.
+#![allow(unused_crate_dependencies)]
+
+mod common;
+
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use gdn::{repo, store::Store};
+use git2::{Oid, Repository};
+
+fn time<T>(name: &str, f: impl FnOnce() -> T) -> (T, Duration) {
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    println!("{name:<24} {elapsed:>12.3?}");
+    (result, elapsed)
+}
+
+fn head_tree(path: &Path) -> anyhow::Result<Oid> {
+    let repository = Repository::open_bare(path)?;
+    Ok(repository.head()?.peel_to_tree()?.id())
+}
+
+fn run(path: &Path) -> anyhow::Result<()> {
+    repo::init(path)?;
+
+    let repo = common::generate();
+    time("initial save", || repo::save(path, repo)).0?;
+
+    let repo = time("load", || repo::load(path, None)).0?;
+    let mut store = Store::load(repo);
+    let id = common::note_id(common::NOTES / 2);
+    store.set_text(id, "Modified".to_string()).unwrap();
+
+    let mut full = store.save();
+    full.dirty = None;
+    let (oid, full_time) = time("full save", || repo::save(path, full));
+    oid?;
+
+    // The full save moved the head, so the store needs to be based on it.
+    let mut store = Store::load(repo::load(path, None)?);
+    store.set_text(id, "Modified again".to_string()).unwrap();
+    let incremental = store.save();
+    let (oid, incremental_time) = time("incremental save", || repo::save(path, incremental));
+    oid?;
+
+    // Saving the same notes in full must result in the same tree.
+    let incremental_tree = head_tree(path)?;
+    let mut full = store.save();
+    full.dirty = None;
+    repo::save(path, full)?;
+    assert_eq!(incremental_tree, head_tree(path)?);
+
+    println!(
+        "speedup                  {:>11.1}x",
+        full_time.as_secs_f64() / incremental_time.as_secs_f64()
+    );
+
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    common::with_temp_repo("save", run)
+}
@@ -0,0 +1,189 @@
+use std::{fs, path::Path};
+
+use anyhow::{Context, anyhow, bail};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    AeadCore, KeyInit, XChaCha20Poly1305, XNonce,
+    aead::{Aead, OsRng, Payload},
+};
+use serde::{Deserialize, Serialize};
+use zeroize::Zeroizing;
+
+/// The only supported cipher.
+pub const CIPHER: &str = "xchacha20poly1305";
+
+/// The only supported key derivation function.
+pub const KDF: &str = "argon2id";
+
+const KEY_LEN: usize = 32;
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+/// The highest key derivation parameters accepted from a [`Scheme`].
+///
+/// The scheme is stored unencrypted, so without limits, a crafted repo could
+/// make deriving its key take any amount of memory and time. The defaults used
+/// by [`Scheme::new`] are far below these.
+const MAX_MEMORY: u32 = 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+/// Encrypted to verify that a key is correct, see [`Scheme::unlock`].
+const CHECK_PLAINTEXT: &[u8] = b"gdn";
+const CHECK_AAD: &[u8] = b"check";
+
+/// A passphrase or the content of a keyfile that a key is derived from.
+#[derive(Clone)]
+pub struct Secret(Zeroizing<Vec<u8>>);
+
+impl Secret {
+    pub fn from_passphrase(passphrase: String) -> Self {
+        Self(Zeroizing::new(passphrase.into_bytes()))
+    }
+
+    pub fn from_keyfile(path: &Path) -> anyhow::Result<Self> {
+        let content =
+            fs::read(path).with_context(|| format!("failed to read keyfile {}", path.display()))?;
+        if content.is_empty() {
+            bail!("keyfile {} is empty", path.display());
+        }
+        Ok(Self(Zeroizing::new(content)))
+    }
+}
+
+/// A key used to encrypt and decrypt the files of a repo.
+#[derive(Clone)]
+pub struct Key(Zeroizing<[u8; KEY_LEN]>);
+
+impl Key {
+    fn cipher(&self) -> XChaCha20Poly1305 {
+        XChaCha20Poly1305::new(self.0.as_ref().into())
+    }
+
+    /// Encrypt data, prefixing it with a random nonce.
+    ///
+    /// The associated data is not part of the result, but must be the same
+    /// when decrypting. It binds the result to its location, so encrypted
+    /// files can't be swapped unnoticed.
+    pub fn encrypt(&self, plaintext: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let payload = Payload {
+            msg: plaintext,
+            aad,
+        };
+        let ciphertext = self
+            .cipher()
+            .encrypt(&nonce, payload)
+            .map_err(|_| anyhow!("failed to encrypt"))?;
+
+        let mut result = nonce.to_vec();
+        result.extend(ciphertext);
+        Ok(result)
+    }
+
+    /// Decrypt data encrypted by [`Self::encrypt`].
+    pub fn decrypt(&self, data: &[u8], aad: &[u8]) -> anyhow::Result<Vec<u8>> {
+        if data.len() < NONCE_LEN {
+            bail!("encrypted data is too short");
+        }
+        let (nonce, ciphertext) = data.split_at(NONCE_LEN);
+        let payload = Payload {
+            msg: ciphertext,
+            aad,
+        };
+        self.cipher()
+            .decrypt(XNonce::from_slice(nonce), payload)
+            .map_err(|_| anyhow!("failed to decrypt, the data may be corrupted"))
+    }
+}
+
+/// How the files of a repo are encrypted.
+///
+/// This is stored unencrypted in the repo and contains everything needed to
+/// derive the key from a [`Secret`], except for the secret itself.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Scheme {
+    pub cipher: String,
+    pub kdf: String,
+    /// Memory cost in KiB.
+    pub memory: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    /// Hex-encoded salt.
+    pub salt: String,
+    /// Hex-encoded known plaintext encrypted with the key, to detect wrong
+    /// secrets.
+    pub check: String,
+}
+
+impl Scheme {
+    /// Create a new scheme with a random salt and derive its key.
+    pub fn new(secret: &Secret) -> anyhow::Result<(Self, Key)> {
+        let params = Params::default();
+        let mut scheme = Self {
+            cipher: CIPHER.to_string(),
+            kdf: KDF.to_string(),
+            memory: params.m_cost(),
+            iterations: params.t_cost(),
+            parallelism: params.p_cost(),
+            salt: hex::encode(rand::random::<[u8; SALT_LEN]>()),
+            check: String::new(),
+        };
+        let key = scheme.derive_key(secret)?;
+        scheme.check = hex::encode(key.encrypt(CHECK_PLAINTEXT, CHECK_AAD)?);
+        Ok((scheme, key))
+    }
+
+    fn derive_key(&self, secret: &Secret) -> anyhow::Result<Key> {
+        if self.cipher != CIPHER {
+            bail!("unsupported cipher {}", self.cipher);
+        }
+        if self.kdf != KDF {
+            bail!("unsupported key derivation function {}", self.kdf);
+        }
+
+        if self.memory > MAX_MEMORY
+            || self.iterations > MAX_ITERATIONS
+            || self.parallelism > MAX_PARALLELISM
+        {
+            bail!(
+                "key derivation parameters are too high (memory {} KiB, {} iterations, parallelism {})",
+                self.memory,
+                self.iterations,
+                self.parallelism
+            );
+        }
+
+        let params = Params::new(
+            self.memory,
+            self.iterations,
+            self.parallelism,
+            Some(KEY_LEN),
+        )
+        .map_err(|err| anyhow!("invalid key derivation parameters: {err}"))?;
+        let salt = hex::decode(&self.salt).context("invalid salt")?;
+
+        let mut key = Zeroizing::new([0; KEY_LEN]);
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(&secret.0, &salt, key.as_mut())
+            .map_err(|err| anyhow!("failed to derive key: {err}"))?;
+        Ok(Key(key))
+    }
+
+    /// Derive the key from a secret, failing if the secret is wrong.
+    pub fn unlock(&self, secret: &Secret) -> anyhow::Result<Key> {
+        let key = self.derive_key(secret)?;
+        let check = hex::decode(&self.check).context("invalid check value")?;
+        match key.decrypt(&check, CHECK_AAD) {
+            Ok(plaintext) if plaintext == CHECK_PLAINTEXT => Ok(key),
+            _ => bail!("wrong passphrase or keyfile"),
+        }
+    }
+}
+
+/// The encryption settings of an unlocked repo.
+#[derive(Clone)]
+pub struct Encryption {
+    pub scheme: Scheme,
+    pub key: Key,
+}
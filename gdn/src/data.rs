@@ -1,8 +1,9 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, time::Duration};
 
 use anyhow::Context;
 use directories::ProjectDirs;
 
+mod backup;
 mod datadir;
 mod lockfile;
 mod v0;
@@ -10,15 +11,23 @@ mod v1;
 
 pub use crate::repo::VERSION as REPO_VERSION;
 
+/// How long to wait for other processes to release the data dir lock.
+pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub use self::{
-    datadir::{LockedDataDir, UnlockedDataDir},
+    backup::{backup_file, backups_dir, create_backup, restore_backup},
+    datadir::{LockedDataDir, SharedLockedDataDir, UnlockedDataDir},
     v1::{
-        State, VERSION, add_repo, load_repo, load_repo_version, load_state, remove_repo,
-        rename_repo, save_repo, select_repo, tidy,
+        State, Transfer, Transferred, VERSION, add_repo, load_attachment, load_repo,
+        load_repo_lazy, load_repo_scheme, load_repo_version, load_state, remove_repo, rename_repo,
+        save_attachment, save_repo, select_repo, tidy, transfer_subtree,
     },
 };
 
 fn migrate(dir: &LockedDataDir) -> anyhow::Result<()> {
+    // Version 0 is just the empty directory, the only data dir version migrated
+    // so far, so there is nothing to back up. Repo migrations are backed up
+    // when the migrated repo is saved, see [`save_repo`].
     loop {
         let version = dir.read_version().context("failed to migrate data dir")?;
         match version {
@@ -30,19 +39,41 @@ fn migrate(dir: &LockedDataDir) -> anyhow::Result<()> {
 }
 
 pub fn read_version(path: PathBuf) -> anyhow::Result<u32> {
-    let dir = UnlockedDataDir::new(path);
+    // A missing data dir is version 0, and reading mustn't create it.
+    if !path.try_exists()? {
+        return Ok(v0::VERSION);
+    }
+    let dir = UnlockedDataDir::new(path).lock_shared_timeout(LOCK_TIMEOUT)?;
     let version = dir.read_version()?;
     Ok(version)
 }
 
-pub fn open(path: PathBuf) -> anyhow::Result<UnlockedDataDir> {
+pub fn open(path: PathBuf) -> anyhow::Result<SharedLockedDataDir> {
     let dir = UnlockedDataDir::new(path);
+    // A missing data dir is version 0, which is reported without creating it.
+    if !dir.path().try_exists()? {
+        dir.require_version(VERSION)?;
+    }
+    let dir = dir.lock_shared_timeout(LOCK_TIMEOUT)?;
     dir.require_version(VERSION)?;
     Ok(dir)
 }
 
+/// Lock the data dir exclusively without migrating it.
+///
+/// Unlike [`open_and_migrate`], this accepts any version, for example to back
+/// up the data dir as it is.
+pub fn open_exclusive(path: PathBuf) -> anyhow::Result<LockedDataDir> {
+    // A missing data dir is version 0, and there is nothing to lock.
+    let dir = UnlockedDataDir::new(path);
+    if !dir.path().try_exists()? {
+        dir.require_version(VERSION)?;
+    }
+    dir.lock_timeout(LOCK_TIMEOUT)
+}
+
 pub fn open_and_migrate(path: PathBuf) -> anyhow::Result<LockedDataDir> {
-    let dir = UnlockedDataDir::new(path).lock()?;
+    let dir = UnlockedDataDir::new(path).lock_timeout(LOCK_TIMEOUT)?;
     migrate(&dir)?;
     dir.require_version(VERSION)?;
     Ok(dir)
@@ -0,0 +1,161 @@
+use std::{
+    fs::{self, File},
+    io::ErrorKind,
+    path::{self, Path, PathBuf},
+};
+
+use anyhow::{Context, bail};
+use jiff::Timestamp;
+
+use super::{LockedDataDir, UnlockedDataDir};
+
+pub fn backups_dir(dir: &UnlockedDataDir) -> PathBuf {
+    dir.path().join("backups")
+}
+
+/// Choose a new backup file path inside the data dir's backups directory.
+///
+/// The name contains the current time down to the microsecond, followed by a
+/// counter in the unlikely case that a backup with that name already exists.
+pub fn backup_file(dir: &UnlockedDataDir) -> anyhow::Result<PathBuf> {
+    let now = Timestamp::now().strftime("%Y%m%dT%H%M%S%.6fZ");
+    let version = dir.read_version()?;
+    let prefix = format!("{}-{now}-v{version}", crate::ABBREVIATED_NAME);
+
+    let mut path = backups_dir(dir).join(format!("{prefix}.tar"));
+    let mut counter = 1;
+    while path.try_exists()? {
+        path = backups_dir(dir).join(format!("{prefix}-{counter}.tar"));
+        counter += 1;
+    }
+    Ok(path)
+}
+
+/// Make a path absolute and resolve symlinks in the part of it that exists.
+fn resolve(path: &Path) -> anyhow::Result<PathBuf> {
+    let mut existing = path::absolute(path)?;
+    let mut missing = vec![];
+    while !existing.try_exists()? {
+        let Some(name) = existing.file_name() else {
+            break;
+        };
+        missing.push(name.to_os_string());
+        existing.pop();
+    }
+    let mut resolved = fs::canonicalize(&existing)?;
+    resolved.extend(missing.into_iter().rev());
+    Ok(resolved)
+}
+
+/// Fail if `target` is inside the data dir, but not in its backups directory.
+///
+/// Backups are excluded from backups, but anything else inside the data dir
+/// would end up archiving the archive being written.
+fn check_target(dir: &UnlockedDataDir, target: &Path) -> anyhow::Result<()> {
+    let data = fs::canonicalize(dir.path())?;
+    let target = resolve(target)?;
+    if target.starts_with(&data) && !target.starts_with(data.join("backups")) {
+        bail!(
+            "backups inside the data dir must be in {}",
+            backups_dir(dir).display()
+        );
+    }
+    Ok(())
+}
+
+/// Whether a top-level entry of the data dir should be left out of backups.
+///
+/// Lock files only make sense for the running process, temporary files are
+/// incomplete by definition, and including older backups would make every
+/// backup larger than the last.
+fn exclude_from_backup(dir: &UnlockedDataDir, path: &Path) -> bool {
+    let hidden = path
+        .file_name()
+        .is_some_and(|name| name.as_encoded_bytes().starts_with(b"."));
+
+    hidden
+        || path == dir.path_lock_file()
+        || path == dir.path_advisory_lock_file()
+        || path == backups_dir(dir)
+}
+
+/// Write the contents of the data dir to a tar archive at `target`.
+///
+/// The data dir must be locked exclusively, since `target` may be inside of
+/// it, see [`backup_file`]. Fails if `target` already exists or if it is inside
+/// the data dir, but not in its backups directory.
+pub fn create_backup(dir: &LockedDataDir, target: &Path) -> anyhow::Result<()> {
+    check_target(dir, target)?;
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = File::create_new(target)
+        .with_context(|| format!("failed to create backup file {}", target.display()))?;
+
+    let mut entries = fs::read_dir(dir.path())?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    entries.sort_unstable();
+
+    let mut builder = tar::Builder::new(file);
+    builder.follow_symlinks(false);
+    for path in entries {
+        if exclude_from_backup(dir, &path) {
+            continue;
+        }
+
+        let name = path.strip_prefix(dir.path())?;
+        if path.is_dir() {
+            builder.append_dir_all(name, &path)?;
+        } else {
+            builder.append_path_with_name(&path, name)?;
+        }
+    }
+    builder.into_inner()?.sync_all()?;
+
+    Ok(())
+}
+
+/// Restore a backup created by [`create_backup`] into a fresh data dir at
+/// `target`.
+///
+/// To avoid overwriting existing data, `target` must either not exist or be
+/// an empty directory. Returns the version of the restored data dir.
+pub fn restore_backup(archive: &Path, target: PathBuf) -> anyhow::Result<u32> {
+    match fs::read_dir(&target) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                bail!("{} is not empty", target.display());
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => {}
+        Err(err) => Err(err)?,
+    }
+
+    let dir = UnlockedDataDir::new(target).lock()?;
+
+    let file = File::open(archive)
+        .with_context(|| format!("failed to open backup file {}", archive.display()))?;
+    tar::Archive::new(file)
+        .unpack(dir.path())
+        .with_context(|| format!("failed to unpack backup file {}", archive.display()))?;
+
+    let version = dir.read_version()?;
+    dir.unlock()?;
+    Ok(version)
+}
+
+/// Back up the data dir before migrating parts of it from an older version.
+///
+/// Only the first call while the data dir is locked creates a backup. An
+/// operation migrating several repos only needs the state from before its
+/// first change.
+pub fn backup_before_migration(dir: &LockedDataDir) -> anyhow::Result<()> {
+    if dir.backed_up() {
+        return Ok(());
+    }
+    let target = backup_file(dir)?;
+    create_backup(dir, &target).context("failed to back up data dir before migration")?;
+    dir.set_backed_up();
+    Ok(())
+}
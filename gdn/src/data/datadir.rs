@@ -1,16 +1,18 @@
 use std::{
+    cell::Cell,
     ffi::{OsStr, OsString},
     fs,
     io::ErrorKind,
     ops::Deref,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 use anyhow::{Context, anyhow, bail};
 use rand::{Rng, distr::Alphanumeric};
 use serde::{Serialize, de::DeserializeOwned};
 
-use super::lockfile::LockFile;
+use super::lockfile::{LockFile, SharedLock};
 
 /// Create a temporary file name derived from an existing file name.
 ///
@@ -82,19 +84,72 @@ impl UnlockedDataDir {
         self.path.join("VERSION")
     }
 
-    fn path_lock_file(&self) -> PathBuf {
+    pub(super) fn path_lock_file(&self) -> PathBuf {
         self.path.join("LOCK")
     }
 
-    pub fn lock(self) -> anyhow::Result<LockedDataDir> {
+    pub(super) fn path_advisory_lock_file(&self) -> PathBuf {
+        self.path.join("LOCK.advisory")
+    }
+
+    fn lock_with_timeout(self, timeout: Duration) -> anyhow::Result<LockedDataDir> {
         fs::create_dir_all(self.path())?;
 
+        let lockfile = LockFile::lock(
+            self.path_lock_file(),
+            &self.path_advisory_lock_file(),
+            timeout,
+        )
+        .with_context(|| format!("failed to lock data dir at {}", self.path.display()))?;
+
         Ok(LockedDataDir {
-            lockfile: LockFile::lock(self.path_lock_file())?,
+            lockfile,
             unlocked: self,
+            backed_up: Cell::new(false),
         })
     }
 
+    /// Lock the data dir, failing immediately if it is already locked.
+    pub fn lock(self) -> anyhow::Result<LockedDataDir> {
+        self.lock_with_timeout(Duration::ZERO)
+    }
+
+    /// Lock the data dir, waiting for at most `timeout` if it is already
+    /// locked.
+    pub fn lock_timeout(self, timeout: Duration) -> anyhow::Result<LockedDataDir> {
+        self.lock_with_timeout(timeout)
+    }
+
+    /// Unlike the exclusive lock, this never creates the data dir, so merely
+    /// reading doesn't leave anything behind. Fails if the data dir doesn't
+    /// exist.
+    fn lock_shared_with_timeout(self, timeout: Duration) -> anyhow::Result<SharedLockedDataDir> {
+        let lock =
+            SharedLock::lock(&self.path_advisory_lock_file(), timeout).with_context(|| {
+                format!(
+                    "failed to lock data dir at {} for reading",
+                    self.path.display()
+                )
+            })?;
+
+        Ok(SharedLockedDataDir {
+            lock,
+            unlocked: self,
+        })
+    }
+
+    /// Lock the data dir for reading, failing immediately if it is locked
+    /// exclusively.
+    pub fn lock_shared(self) -> anyhow::Result<SharedLockedDataDir> {
+        self.lock_shared_with_timeout(Duration::ZERO)
+    }
+
+    /// Lock the data dir for reading, waiting for at most `timeout` if it is
+    /// locked exclusively.
+    pub fn lock_shared_timeout(self, timeout: Duration) -> anyhow::Result<SharedLockedDataDir> {
+        self.lock_shared_with_timeout(timeout)
+    }
+
     pub(super) fn read_string(&self, path: &Path) -> anyhow::Result<String> {
         fs::read_to_string(path).with_context(|| format!("failed to read {}", path.display()))
     }
@@ -137,9 +192,35 @@ impl UnlockedDataDir {
     }
 }
 
+/// A data dir that may be read, but not written.
+///
+/// While it exists, no [`LockedDataDir`] can exist for the same path, so the
+/// data dir can't change while it is being read.
+pub struct SharedLockedDataDir {
+    unlocked: UnlockedDataDir,
+    lock: SharedLock,
+}
+
+impl SharedLockedDataDir {
+    pub fn unlock(self) -> anyhow::Result<UnlockedDataDir> {
+        self.lock.unlock()?;
+        Ok(self.unlocked)
+    }
+}
+
+impl Deref for SharedLockedDataDir {
+    type Target = UnlockedDataDir;
+
+    fn deref(&self) -> &Self::Target {
+        &self.unlocked
+    }
+}
+
 pub struct LockedDataDir {
     unlocked: UnlockedDataDir,
     lockfile: LockFile,
+    /// Whether the data dir was backed up before a migration while locked.
+    backed_up: Cell<bool>,
 }
 
 impl LockedDataDir {
@@ -162,6 +243,14 @@ impl LockedDataDir {
     pub(super) fn write_version(&self, version: u32) -> anyhow::Result<()> {
         self.write_string(&self.path_version_file(), &format!("{version}\n"))
     }
+
+    pub(super) fn backed_up(&self) -> bool {
+        self.backed_up.get()
+    }
+
+    pub(super) fn set_backed_up(&self) {
+        self.backed_up.set(true);
+    }
 }
 
 impl Deref for LockedDataDir {
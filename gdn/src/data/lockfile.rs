@@ -1,19 +1,110 @@
 use std::{
-    fs::{self, File},
+    fs::{self, File, OpenOptions, TryLockError},
     io,
-    path::PathBuf,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, Instant},
 };
 
+const BACKOFF_MIN: Duration = Duration::from_millis(10);
+const BACKOFF_MAX: Duration = Duration::from_millis(500);
+
+/// Repeatedly call `f` until it returns a value, an error, or the timeout
+/// elapses.
+///
+/// The function signals that it should be retried by returning `Ok(None)`. The
+/// time between attempts doubles after each attempt, starting at
+/// [`BACKOFF_MIN`] and capped at [`BACKOFF_MAX`].
+fn retry_with_backoff<T>(
+    path: &Path,
+    timeout: Duration,
+    mut f: impl FnMut() -> io::Result<Option<T>>,
+) -> io::Result<T> {
+    let start = Instant::now();
+    let mut backoff = BACKOFF_MIN;
+    loop {
+        if let Some(result) = f()? {
+            return Ok(result);
+        }
+
+        let remaining = timeout.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!("{} is held by another process", path.display()),
+            ));
+        }
+
+        thread::sleep(backoff.min(remaining));
+        backoff = (backoff * 2).min(BACKOFF_MAX);
+    }
+}
+
+fn open_advisory(path: &Path) -> io::Result<File> {
+    OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(path)
+}
+
+/// An exclusive lock on a directory.
+///
+/// The lock consists of two parts: An OS advisory lock (e.g. `flock`) on a
+/// file that is never deleted, and a lock file that only exists while the lock
+/// is held. The advisory lock allows waiting processes to queue up, while the
+/// lock file is visible to anyone looking at the directory.
 pub struct LockFile {
     path: PathBuf,
     file: Option<File>,
+    advisory: File,
 }
 
 impl LockFile {
-    pub fn lock(path: PathBuf) -> io::Result<Self> {
+    fn try_lock(path: &Path, advisory: &File) -> io::Result<Option<File>> {
+        match advisory.try_lock() {
+            Ok(()) => {}
+            Err(TryLockError::WouldBlock) => return Ok(None),
+            Err(TryLockError::Error(err)) => return Err(err),
+        }
+
+        // If the lock file still exists even though we hold the advisory lock,
+        // it was either left behind by a crashed process or created by a
+        // process that doesn't know about advisory locks. Either way, we must
+        // not take the lock, and waiting won't help.
+        match File::create_new(path) {
+            Ok(file) => Ok(Some(file)),
+            Err(err) if err.kind() == io::ErrorKind::AlreadyExists => {
+                advisory.unlock()?;
+                Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!(
+                        "{} is stale, remove it if no other program is using the directory",
+                        path.display()
+                    ),
+                ))
+            }
+            Err(err) => {
+                advisory.unlock()?;
+                Err(err)
+            }
+        }
+    }
+
+    /// Acquire the lock, waiting for at most `timeout`.
+    ///
+    /// A timeout of [`Duration::ZERO`] fails immediately if the lock is held
+    /// by someone else. Fails immediately if the lock file was left behind.
+    pub fn lock(path: PathBuf, advisory_path: &Path, timeout: Duration) -> io::Result<Self> {
+        let advisory = open_advisory(advisory_path)?;
+
+        let file = retry_with_backoff(&path, timeout, || Self::try_lock(&path, &advisory))?;
+
         Ok(Self {
-            file: Some(File::create_new(&path)?),
             path,
+            file: Some(file),
+            advisory,
         })
     }
 
@@ -25,6 +116,7 @@ impl LockFile {
 
         drop(file);
         fs::remove_file(&self.path)?;
+        self.advisory.unlock()?;
         Ok(())
     }
 
@@ -35,8 +127,45 @@ impl LockFile {
 
 impl Drop for LockFile {
     fn drop(&mut self) {
+        if self.file.is_none() {
+            return; // Already unlocked
+        }
+
         if let Err(_err) = self.unlock_ref() {
             // TODO Log error
         }
     }
 }
+
+/// A shared lock on a directory.
+///
+/// Any number of shared locks can be held at the same time, but not while a
+/// [`LockFile`] is held. Only the advisory lock is used, so no lock file is
+/// created.
+pub struct SharedLock {
+    advisory: File,
+}
+
+impl SharedLock {
+    fn try_lock(advisory: &File) -> io::Result<Option<()>> {
+        match advisory.try_lock_shared() {
+            Ok(()) => Ok(Some(())),
+            Err(TryLockError::WouldBlock) => Ok(None),
+            Err(TryLockError::Error(err)) => Err(err),
+        }
+    }
+
+    /// Acquire the lock, waiting for at most `timeout`.
+    ///
+    /// A timeout of [`Duration::ZERO`] fails immediately if an exclusive lock
+    /// is held by someone else.
+    pub fn lock(advisory_path: &Path, timeout: Duration) -> io::Result<Self> {
+        let advisory = open_advisory(advisory_path)?;
+        retry_with_backoff(advisory_path, timeout, || Self::try_lock(&advisory))?;
+        Ok(Self { advisory })
+    }
+
+    pub fn unlock(self) -> io::Result<()> {
+        self.advisory.unlock()
+    }
+}
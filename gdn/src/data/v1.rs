@@ -1,15 +1,21 @@
-use std::{collections::HashMap, fs, path::PathBuf};
+use std::{
+    collections::{BTreeSet, HashMap},
+    fs,
+    path::PathBuf,
+};
 
-use anyhow::anyhow;
+use anyhow::{Context, anyhow, bail};
 use git2::Oid;
 use serde::{Deserialize, Serialize};
 
 use crate::{
-    ids::RepoId,
-    repo::{self, Repo},
+    crypto::{Scheme, Secret},
+    ids::{self, NoteId, RepoId},
+    repo::{self, LazyRepo, Repo},
+    store::Store,
 };
 
-use super::{LockedDataDir, UnlockedDataDir};
+use super::{LockedDataDir, UnlockedDataDir, backup};
 
 pub const VERSION: u32 = 1;
 
@@ -21,36 +27,80 @@ pub struct State {
 
 impl State {
     pub fn normalize(&mut self) {
-        if let Some(selected) = self.selected_repo {
-            if !self.repos.contains_key(&selected) {
-                self.selected_repo = None;
-            }
+        if let Some(selected) = self.selected_repo
+            && !self.repos.contains_key(&selected)
+        {
+            self.selected_repo = None;
         }
     }
 
-    pub fn resolve_repo_identifier(&self, identifier: &str) -> Option<RepoId> {
+    /// The repos an identifier refers to without abbreviation, sorted by name
+    /// and id.
+    ///
+    /// An identifier matching a repo id exactly, ignoring case, refers to that
+    /// repo only. Otherwise, it refers to all repos with exactly that name.
+    pub fn exact_repo_candidates(&self, identifier: &str) -> Vec<RepoId> {
         // If the identifier is a valid repo id, always interpret it as such.
         // There must always be an unambiguous way to refer to repos.
-        if let Ok(id) = identifier.parse::<RepoId>() {
-            if self.repos.contains_key(&id) {
-                return Some(id);
-            }
+        if let Some(id) = self
+            .repos
+            .keys()
+            .find(|id| id.to_string().eq_ignore_ascii_case(identifier))
+        {
+            return vec![*id];
         }
 
-        // Otherwise, interpret the identifier as a repo name and find the
-        // corresponding id.
-        let matching = self
+        // Names should be unique, but older data dirs may still contain
+        // duplicates.
+        let mut result = self
             .repos
             .iter()
             .filter(|(_, name)| *name == identifier)
             .map(|(id, _)| *id)
             .collect::<Vec<_>>();
+        result.sort_unstable_by_key(|id| (&self.repos[id], *id));
+        result
+    }
+
+    /// The repos an identifier could refer to, sorted by name and id.
+    ///
+    /// An identifier matching a repo id or a repo name exactly refers to that
+    /// repo only, in that order, see [`Self::exact_repo_candidates`].
+    /// Otherwise, it refers to all repos whose name starts with it or whose id
+    /// starts with it, see [`ids::matching`].
+    pub fn repo_candidates(&self, identifier: &str) -> Vec<RepoId> {
+        let mut result = self.exact_repo_candidates(identifier);
 
-        match matching.first() {
-            None => None,
-            Some(_) if matching.len() > 1 => None,
-            Some(id) => Some(*id),
+        if result.is_empty() && !identifier.is_empty() {
+            let by_id = ids::matching(self.repos.keys().copied(), identifier);
+            result = self
+                .repos
+                .iter()
+                .filter(|(id, name)| name.starts_with(identifier) || by_id.contains(id))
+                .map(|(id, _)| *id)
+                .collect();
+            result.sort_unstable_by_key(|id| (&self.repos[id], *id));
         }
+
+        result
+    }
+
+    /// The repo an identifier refers to, if it refers to exactly one repo.
+    ///
+    /// See [`Self::repo_candidates`].
+    pub fn resolve_repo_identifier(&self, identifier: &str) -> Option<RepoId> {
+        match self.repo_candidates(identifier)[..] {
+            [id] => Some(id),
+            _ => None,
+        }
+    }
+
+    /// The repo with a name, if there is one.
+    pub fn repo_by_name(&self, name: &str) -> Option<RepoId> {
+        self.repos
+            .iter()
+            .find(|(_, it)| *it == name)
+            .map(|(id, _)| *id)
     }
 }
 
@@ -79,18 +129,199 @@ pub fn load_repo_version(dir: &UnlockedDataDir, id: RepoId) -> anyhow::Result<u3
     repo::load_version(&repo_dir(dir, id))
 }
 
-pub fn load_repo(dir: &UnlockedDataDir, id: RepoId) -> anyhow::Result<Repo> {
-    repo::load(&repo_dir(dir, id))
+pub fn load_repo_scheme(dir: &UnlockedDataDir, id: RepoId) -> anyhow::Result<Option<Scheme>> {
+    repo::load_scheme(&repo_dir(dir, id))
+}
+
+pub fn load_repo(
+    dir: &UnlockedDataDir,
+    id: RepoId,
+    secret: Option<&Secret>,
+) -> anyhow::Result<Repo> {
+    repo::load(&repo_dir(dir, id), secret)
+}
+
+pub fn load_repo_lazy(
+    dir: &UnlockedDataDir,
+    id: RepoId,
+    secret: Option<&Secret>,
+) -> anyhow::Result<LazyRepo> {
+    repo::load_lazy(&repo_dir(dir, id), secret)
 }
 
+/// Save a repo in the current version.
+///
+/// If the repo is stored in an older version, the data dir is backed up
+/// first, since the migrated repo replaces it, see
+/// [`backup::backup_before_migration`]. Version 0 is a repo without commits,
+/// which has nothing to back up.
 pub fn save_repo(dir: &LockedDataDir, id: RepoId, repo: Repo) -> anyhow::Result<Oid> {
-    repo::save(&repo_dir(dir, id), repo)
+    let prepared = prepare_save_repo(dir, id, repo)?;
+    repo::finish_save(&repo_dir(dir, id), &prepared)
+}
+
+/// Everything [`save_repo`] does except for moving the repo's head.
+fn prepare_save_repo(
+    dir: &LockedDataDir,
+    id: RepoId,
+    repo: Repo,
+) -> anyhow::Result<repo::PreparedSave> {
+    let version = load_repo_version(dir, id)?;
+    if (1..repo::VERSION).contains(&version) {
+        backup::backup_before_migration(dir)?;
+    }
+    repo::prepare_save(&repo_dir(dir, id), repo)
+}
+
+/// Store the content of an attachment in a repo, see
+/// [`repo::write_attachment`].
+///
+/// Attachments are stored unencrypted, so encrypted repos can't have any.
+pub fn save_attachment(dir: &LockedDataDir, id: RepoId, content: &[u8]) -> anyhow::Result<String> {
+    if load_repo_scheme(dir, id)?.is_some() {
+        bail!("encrypted repos can't have attachments, since they would be stored unencrypted");
+    }
+    repo::write_attachment(&repo_dir(dir, id), content)
+}
+
+pub fn load_attachment(dir: &UnlockedDataDir, id: RepoId, hash: &str) -> anyhow::Result<Vec<u8>> {
+    repo::read_attachment(&repo_dir(dir, id), hash)
+}
+
+/// Whether [`transfer_subtree`] keeps the notes in the source repo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Copy,
+    Move,
+}
+
+/// The outcome of [`transfer_subtree`].
+pub struct Transferred {
+    /// The id of the transferred note in the target repo.
+    pub root: NoteId,
+    /// How many notes were added to the target repo.
+    pub notes: usize,
+    /// How many notes were deleted from the source repo.
+    pub removed: usize,
+    /// The commit in the source repo, if it was changed.
+    pub source: Option<Oid>,
+    /// The commit in the target repo.
+    pub target: Oid,
+}
+
+/// Copy or move a note and all of its descendants from `source`, the loaded
+/// repo `from`, to a different repo.
+///
+/// Copies get new ids, while moved notes keep theirs and are removed from the
+/// source repo without keeping them in its trash. Moving fails if the subtree
+/// has descendants that can also be reached from outside of it, see
+/// [`Store::shared_descendants`]. They would have to stay in the source repo
+/// while also being moved, leaving two repos with the same note. Such
+/// subtrees can be copied instead.
+///
+/// The notes' attachments are copied along with them. Each changed repo gets
+/// a single commit. Both commits are written before either repo's head moves,
+/// and if the source repo's head can't be moved, the target repo's head is
+/// moved back, so a failed move never leaves the notes in both repos.
+pub fn transfer_subtree(
+    dir: &LockedDataDir,
+    id: NoteId,
+    from: RepoId,
+    source: Repo,
+    to: RepoId,
+    to_secret: Option<&Secret>,
+    transfer: Transfer,
+) -> anyhow::Result<Transferred> {
+    if from == to {
+        bail!("source and target repo must be different");
+    }
+
+    let mut source = Store::load(source);
+    let mut target = Store::load(load_repo(dir, to, to_secret)?);
+
+    let subtree = source
+        .extract_subtree(id)
+        .ok_or_else(|| anyhow!("no note with id {id}"))?;
+    let subtree = match transfer {
+        Transfer::Copy => subtree.with_fresh_ids(|id| target.is_used(id)),
+        Transfer::Move => {
+            let shared = source.shared_descendants(id).len();
+            if shared > 0 {
+                bail!(
+                    "{shared} descendants of {id} can also be reached from outside of it, copy it instead"
+                );
+            }
+            subtree
+        }
+    };
+
+    let hashes = subtree
+        .notes
+        .iter()
+        .flat_map(|it| &it.meta.attachments)
+        .map(|it| it.hash.as_str())
+        .collect::<BTreeSet<_>>();
+    for hash in hashes {
+        let content = load_attachment(dir, from, hash)?;
+        save_attachment(dir, to, &content)?;
+    }
+
+    let root = subtree.root;
+    let notes = subtree.notes.len();
+    target
+        .insert_subtree(subtree)
+        .ok_or_else(|| anyhow!("some of the notes already exist in the target repo"))?;
+    let target = prepare_save_repo(dir, to, target.save())?;
+
+    let (removed, source) = match transfer {
+        Transfer::Copy => (0, None),
+        Transfer::Move => {
+            // The notes live on in the target repo, so they don't belong in
+            // the source repo's trash.
+            let removed = source.delete_subtree(id);
+            for id in &removed {
+                source.purge(*id);
+            }
+            let removed = removed.len();
+            (removed, Some(prepare_save_repo(dir, from, source.save())?))
+        }
+    };
+
+    // The target's head moves first, so no notes are lost if that fails.
+    let target_path = repo_dir(dir, to);
+    let target_oid = repo::finish_save(&target_path, &target)?;
+    let source = match source {
+        None => None,
+        Some(source) => {
+            match repo::finish_save(&repo_dir(dir, from), &source) {
+                Ok(oid) => Some(oid),
+                Err(err) => {
+                    repo::undo_save(&target_path, &target).with_context(|| {
+                    format!("failed to roll back target repo after failing to save source repo: {err:#}")
+                })?;
+                    return Err(err);
+                }
+            }
+        }
+    };
+
+    Ok(Transferred {
+        root,
+        notes,
+        removed,
+        source,
+        target: target_oid,
+    })
 }
 
+/// Add a new repo. Its name must not be used by any other repo.
 pub fn add_repo(dir: &LockedDataDir, name: String) -> anyhow::Result<RepoId> {
     let id = RepoId::new();
 
     let mut state = load_state(dir)?;
+    if let Some(other) = state.repo_by_name(&name) {
+        bail!("repo {other} is already called {name:?}");
+    }
     state.repos.insert(id, name);
     save_state(dir, state)?;
 
@@ -111,8 +342,14 @@ pub fn remove_repo(dir: &LockedDataDir, id: RepoId) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Rename a repo. The new name must not be used by any other repo.
 pub fn rename_repo(dir: &LockedDataDir, id: RepoId, name: String) -> anyhow::Result<()> {
     let mut state = load_state(dir)?;
+    if let Some(other) = state.repo_by_name(&name)
+        && other != id
+    {
+        bail!("repo {other} is already called {name:?}");
+    }
     *state
         .repos
         .get_mut(&id)
@@ -141,3 +378,66 @@ pub fn tidy(dir: &LockedDataDir) -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{env, path::Path, process};
+
+    use git2::Repository;
+
+    use super::*;
+
+    fn head(dir: &UnlockedDataDir, id: RepoId) -> Oid {
+        let repository = Repository::open_bare(repo_dir(dir, id)).unwrap();
+        repository.head().unwrap().target().unwrap()
+    }
+
+    fn contains(dir: &UnlockedDataDir, id: RepoId, note: NoteId) -> bool {
+        let repo = load_repo(dir, id, None).unwrap();
+        repo.notes.iter().any(|it| it.id == note)
+    }
+
+    fn move_with_locked_source(path: &Path) -> anyhow::Result<()> {
+        let dir = crate::data::open_and_migrate(path.to_path_buf())?;
+        let from = add_repo(&dir, "from".to_string())?;
+        let to = add_repo(&dir, "to".to_string())?;
+
+        let mut store = Store::new();
+        let id = store.create("parent".to_string());
+        let child = store.create("child".to_string());
+        store.set_children(id, vec![child]);
+        save_repo(&dir, from, store.save())?;
+        let before = save_repo(&dir, to, Store::new().save())?;
+
+        // Git refuses to move a ref while its lock file exists.
+        let repository = Repository::open_bare(repo_dir(&dir, from))?;
+        let branch = repository.head()?.name().unwrap().to_string();
+        let lock = repo_dir(&dir, from).join(format!("{branch}.lock"));
+        fs::write(&lock, "")?;
+
+        let source = load_repo(&dir, from, None)?;
+        let result = transfer_subtree(&dir, id, from, source, to, None, Transfer::Move);
+        assert!(result.is_err());
+        assert_eq!(head(&dir, to), before);
+        assert!(contains(&dir, from, id));
+        assert!(!contains(&dir, to, id));
+
+        fs::remove_file(&lock)?;
+        let source = load_repo(&dir, from, None)?;
+        let result = transfer_subtree(&dir, id, from, source, to, None, Transfer::Move)?;
+        assert_eq!((result.notes, result.removed), (2, 2));
+        assert_eq!(head(&dir, to), result.target);
+        assert!(!contains(&dir, from, id));
+        assert!(contains(&dir, to, id));
+
+        Ok(())
+    }
+
+    #[test]
+    fn failed_moves_change_neither_repo() {
+        let path = env::temp_dir().join(format!("gdn-test-transfer-{}", process::id()));
+        let result = move_with_locked_source(&path);
+        fs::remove_dir_all(&path).unwrap();
+        result.unwrap();
+    }
+}
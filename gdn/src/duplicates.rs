@@ -0,0 +1,157 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::{ids::NoteId, store::Store};
+
+/// A group of notes with the same or similar texts, see [`find`].
+pub struct Duplicates {
+    /// The notes of the group, sorted by id, so the oldest note comes first.
+    pub ids: Vec<NoteId>,
+    /// Whether all notes have the same text, ignoring case and whitespace.
+    pub exact: bool,
+}
+
+/// Lowercase a text and collapse all whitespace into single spaces.
+fn normalize(text: &str) -> String {
+    text.split_whitespace()
+        .map(|it| it.to_lowercase())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The character trigrams of a normalized text.
+///
+/// The text is padded with spaces, so even short texts have trigrams.
+fn trigrams(text: &str) -> HashSet<[char; 3]> {
+    let chars = format!(" {text} ").chars().collect::<Vec<_>>();
+    chars.windows(3).map(|it| [it[0], it[1], it[2]]).collect()
+}
+
+fn jaccard(a: &HashSet<[char; 3]>, b: &HashSet<[char; 3]>) -> f64 {
+    let common = a.intersection(b).count();
+    let total = a.len() + b.len() - common;
+    if total == 0 {
+        return 1.0;
+    }
+    common as f64 / total as f64
+}
+
+/// How similar two texts are, from 0 for nothing in common to 1 for the same
+/// text, ignoring case and whitespace.
+///
+/// This is the Jaccard similarity of the sets of character trigrams of the
+/// texts.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    jaccard(&trigrams(&normalize(a)), &trigrams(&normalize(b)))
+}
+
+/// How many of the rarest trigrams of a set must be compared so that any set
+/// with a similarity of at least the threshold shares at least one of them.
+fn prefix_len(len: usize, threshold: f64) -> usize {
+    let required = (threshold * len as f64).ceil() as usize;
+    (len + 1).saturating_sub(required).min(len)
+}
+
+struct UnionFind(Vec<usize>);
+
+impl UnionFind {
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.0[i] != i {
+            self.0[i] = self.0[self.0[i]];
+            i = self.0[i];
+        }
+        i
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        self.0[a.max(b)] = a.min(b);
+    }
+}
+
+/// Find groups of notes whose texts have a [`similarity`] of at least the
+/// threshold, sorted by their oldest note.
+///
+/// Notes with the same text, ignoring case and whitespace, are always
+/// duplicates, and with a threshold of 1 or more only those are found. Notes
+/// that are similar to a note that is similar to another note end up in the
+/// same group. Notes without text are never duplicates.
+///
+/// Instead of comparing every pair of notes, only notes sharing one of their
+/// rarest trigrams are compared, which is enough to find all similar pairs.
+pub fn find(store: &Store, threshold: f64) -> Vec<Duplicates> {
+    let mut texts = HashMap::<String, Vec<NoteId>>::new();
+    for id in store.ids() {
+        let text = normalize(&store.get(id).unwrap().text);
+        if !text.is_empty() {
+            texts.entry(text).or_default().push(id);
+        }
+    }
+    let texts = texts.into_iter().collect::<Vec<_>>();
+
+    // Texts are compared instead of notes, since notes with the same text are
+    // duplicates anyway.
+    let mut groups = UnionFind((0..texts.len()).collect());
+    if threshold < 1.0 {
+        let sets = texts
+            .iter()
+            .map(|(text, _)| trigrams(text))
+            .collect::<Vec<_>>();
+
+        let mut frequencies = HashMap::<[char; 3], usize>::new();
+        for trigram in sets.iter().flatten() {
+            *frequencies.entry(*trigram).or_default() += 1;
+        }
+        let rarest_first = |set: &HashSet<[char; 3]>| {
+            let mut result = set.iter().copied().collect::<Vec<_>>();
+            result.sort_unstable_by_key(|it| (frequencies[it], *it));
+            result
+        };
+
+        // Sets are visited from smallest to largest, and each is compared
+        // with the visited sets that are large enough to be similar to it.
+        let mut order = (0..sets.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| sets[*i].len());
+        let mut visited = HashMap::<[char; 3], Vec<usize>>::new();
+        for i in order {
+            let set = &sets[i];
+            let prefix = rarest_first(set);
+            let prefix = &prefix[..prefix_len(set.len(), threshold)];
+
+            let mut candidates = HashSet::new();
+            for trigram in prefix {
+                candidates.extend(visited.get(trigram).into_iter().flatten().copied());
+            }
+            for j in candidates {
+                let min_len = threshold * set.len() as f64;
+                if sets[j].len() as f64 >= min_len && jaccard(set, &sets[j]) >= threshold {
+                    groups.union(i, j);
+                }
+            }
+
+            for trigram in prefix {
+                visited.entry(*trigram).or_default().push(i);
+            }
+        }
+    }
+
+    let mut result = HashMap::<usize, (Vec<NoteId>, usize)>::new();
+    for (i, (_, ids)) in texts.iter().enumerate() {
+        let group = result.entry(groups.find(i)).or_default();
+        group.0.extend(ids);
+        group.1 += 1;
+    }
+
+    let mut result = result
+        .into_values()
+        .filter(|(ids, _)| ids.len() > 1)
+        .map(|(mut ids, texts)| {
+            ids.sort_unstable();
+            Duplicates {
+                ids,
+                exact: texts == 1,
+            }
+        })
+        .collect::<Vec<_>>();
+    result.sort_unstable_by_key(|it| it.ids[0]);
+    result
+}
@@ -0,0 +1,53 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::{ids::NoteId, store::Store};
+
+pub mod anki;
+pub mod json;
+pub mod markdown;
+pub mod opml;
+pub mod org;
+
+/// The order in which notes are exported.
+pub(crate) struct Walk {
+    /// The notes the walk was started from. These are the roots of the store,
+    /// followed by one note per cycle that isn't reachable from any root.
+    pub starts: Vec<NoteId>,
+    /// Every note in the store, in breadth-first order.
+    pub order: Vec<NoteId>,
+}
+
+impl Walk {
+    pub fn new(store: &Store) -> Self {
+        let roots = store.roots();
+        let mut ids = store.ids().collect::<Vec<_>>();
+        ids.sort_unstable();
+
+        let mut starts = vec![];
+        let mut order = vec![];
+        let mut visited = HashSet::new();
+
+        // Notes that aren't visited after walking from all roots must be part
+        // of (or reachable from) a cycle without a root. Starting from the
+        // smallest of them ensures the result is deterministic.
+        for start in roots.into_iter().chain(ids) {
+            if !visited.insert(start) {
+                continue;
+            }
+
+            starts.push(start);
+            let mut queue = VecDeque::from([start]);
+            while let Some(id) = queue.pop_front() {
+                order.push(id);
+                let Some(note) = store.get(id) else { continue };
+                for child in note.children {
+                    if visited.insert(child) {
+                        queue.push_back(child);
+                    }
+                }
+            }
+        }
+
+        Self { starts, order }
+    }
+}
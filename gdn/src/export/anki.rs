@@ -0,0 +1,60 @@
+use std::fmt::Write;
+
+use crate::{
+    review::{Card, cards},
+    store::Store,
+};
+
+/// A GUID that stays the same across exports, so Anki updates existing cards
+/// instead of creating duplicates when a deck is imported again.
+pub fn guid(card: &Card) -> String {
+    format!("{}-{}", crate::ABBREVIATED_NAME, card.id)
+}
+
+/// Escape text as HTML for a single TSV field.
+///
+/// Tabs and line breaks would otherwise end the field or row, and Anki
+/// interprets fields starting with `"` as quoted.
+fn escape_field(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\t' => result.push_str("&#9;"),
+            '\n' => result.push_str("<br>"),
+            '\r' => {}
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Export all cards as a TSV file that Anki can import into the given deck.
+///
+/// The file starts with headers telling Anki how to interpret it. Each row
+/// has the columns GUID, front and back, using the "Basic" note type.
+pub fn export(store: &Store, deck: &str) -> String {
+    let mut result = String::new();
+    writeln!(result, "#separator:tab").unwrap();
+    writeln!(result, "#html:true").unwrap();
+    writeln!(result, "#notetype:Basic").unwrap();
+    writeln!(result, "#deck:{}", deck.replace(['\t', '\n', '\r'], " ")).unwrap();
+    writeln!(result, "#guid column:1").unwrap();
+    writeln!(result, "#columns:GUID\tFront\tBack").unwrap();
+
+    for card in cards(store) {
+        writeln!(
+            result,
+            "{}\t{}\t{}",
+            guid(&card),
+            escape_field(&card.front),
+            escape_field(&card.back)
+        )
+        .unwrap();
+    }
+
+    result
+}
@@ -0,0 +1,236 @@
+use std::{collections::BTreeMap, io::Write};
+
+use anyhow::Context;
+use base64::{Engine, engine::general_purpose::STANDARD as BASE64};
+use jiff::{Timestamp, Zoned};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ids::{NoteId, RepoId},
+    repo::{Attachment, Metadata, Note, Repo, Review, TrashedNote, TrashedParent},
+};
+
+/// The version of the dump format, not to be confused with the repo version.
+///
+/// Version 2 added reviews and the trash, version 3 attachments. Dumps of older
+/// versions can still be imported.
+pub const VERSION: u32 = 3;
+
+/// Information about the repo a dump was created from.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct RepoInfo {
+    pub id: RepoId,
+    pub name: String,
+}
+
+/// Information about a dump, preceding its notes.
+#[derive(Serialize, Deserialize)]
+pub struct Header {
+    /// Always [`crate::TECHNICAL_NAME`], to identify the file.
+    pub format: String,
+    /// The version of the dump format, see [`VERSION`].
+    pub version: u32,
+    pub repo: Option<RepoInfo>,
+    pub exported_at: String,
+}
+
+impl Header {
+    pub fn new(repo: Option<RepoInfo>) -> Self {
+        Self {
+            format: crate::TECHNICAL_NAME.to_string(),
+            version: VERSION,
+            repo,
+            exported_at: Zoned::now().to_string(),
+        }
+    }
+}
+
+/// When a note in the trash was deleted and where it used to be, see
+/// [`TrashedNote`].
+#[derive(Serialize, Deserialize)]
+pub struct DumpTrash {
+    pub deleted: Timestamp,
+    pub parents: Vec<TrashedParent>,
+}
+
+/// A file attached to a note in a dump, see [`Attachment`].
+#[derive(Serialize, Deserialize)]
+pub struct DumpAttachment {
+    pub name: String,
+    /// The base64-encoded content of the file.
+    pub content: String,
+}
+
+/// A single note in a dump, along with its review state and attachments.
+///
+/// This is deliberately separate from [`Note`] so the dump format stays stable
+/// even if the repo format changes.
+#[derive(Serialize, Deserialize)]
+pub struct DumpNote {
+    pub id: NoteId,
+    pub text: String,
+    pub children: Vec<NoteId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<Timestamp>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<DumpAttachment>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<Review>,
+    /// Only present if the note is in the trash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trashed: Option<DumpTrash>,
+}
+
+impl DumpNote {
+    /// Dump a note, reading the content of its attachments by their hash.
+    pub fn from_note(
+        note: &Note,
+        review: Option<&Review>,
+        load_attachment: &mut impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        let attachments = note
+            .meta
+            .attachments
+            .iter()
+            .map(|it| {
+                let content = load_attachment(&it.hash).with_context(|| {
+                    format!("failed to read attachment {} of {}", it.name, note.id)
+                })?;
+                Ok(DumpAttachment {
+                    name: it.name.clone(),
+                    content: BASE64.encode(content),
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        Ok(Self {
+            id: note.id,
+            text: note.text.clone(),
+            children: note.children.clone(),
+            modified: note.meta.modified,
+            device: note.meta.device.clone(),
+            properties: note.meta.properties.clone(),
+            attachments,
+            review: review.cloned(),
+            trashed: None,
+        })
+    }
+
+    pub fn from_trashed(
+        trashed: &TrashedNote,
+        load_attachment: &mut impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            trashed: Some(DumpTrash {
+                deleted: trashed.deleted,
+                parents: trashed.parents.clone(),
+            }),
+            ..Self::from_note(&trashed.note, trashed.review.as_ref(), load_attachment)?
+        })
+    }
+
+    /// Split the dumped note into the note itself, its review state and where
+    /// it was deleted from, if it is in the trash.
+    ///
+    /// The content of its attachments is stored via `save_attachment`, which
+    /// returns the hash to refer to it by.
+    pub fn into_parts(
+        self,
+        save_attachment: &mut impl FnMut(&[u8]) -> anyhow::Result<String>,
+    ) -> anyhow::Result<(Note, Option<Review>, Option<DumpTrash>)> {
+        let attachments = self
+            .attachments
+            .into_iter()
+            .map(|it| {
+                let content = BASE64.decode(&it.content).with_context(|| {
+                    format!("invalid content of attachment {} of {}", it.name, self.id)
+                })?;
+                Ok(Attachment {
+                    name: it.name,
+                    hash: save_attachment(&content)?,
+                    size: content.len() as u64,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let note = Note {
+            id: self.id,
+            text: self.text,
+            children: self.children,
+            meta: Metadata {
+                modified: self.modified,
+                device: self.device,
+                properties: self.properties,
+                attachments,
+            },
+        };
+        Ok((note, self.review, self.trashed))
+    }
+}
+
+/// A whole repo as a single JSON document.
+#[derive(Serialize, Deserialize)]
+pub struct Dump {
+    #[serde(flatten)]
+    pub header: Header,
+    pub notes: Vec<DumpNote>,
+}
+
+/// All notes of a repo including those in the trash, sorted by id.
+fn dump_notes(
+    repo: &Repo,
+    mut load_attachment: impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<Vec<DumpNote>> {
+    let mut notes = vec![];
+    for note in &repo.notes {
+        let review = repo.reviews.get(&note.id);
+        notes.push(DumpNote::from_note(note, review, &mut load_attachment)?);
+    }
+    for trashed in &repo.trash {
+        notes.push(DumpNote::from_trashed(trashed, &mut load_attachment)?);
+    }
+    notes.sort_unstable_by_key(|it| it.id);
+    Ok(notes)
+}
+
+/// Write every note of a repo as a single JSON document.
+///
+/// The dump contains everything, including reviews, the notes in the trash and
+/// the content of attachments, which is read via `load_attachment`. Notes are
+/// sorted by id so dumps of the same repo can be compared easily.
+pub fn export(
+    writer: impl Write,
+    repo: &Repo,
+    info: Option<RepoInfo>,
+    load_attachment: impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<()> {
+    let dump = Dump {
+        header: Header::new(info),
+        notes: dump_notes(repo, load_attachment)?,
+    };
+    serde_json::to_writer_pretty(writer, &dump)?;
+    Ok(())
+}
+
+/// Write every note of a repo as a JSON lines stream.
+///
+/// The first line contains the [`Header`], every following line a single
+/// [`DumpNote`]. Otherwise, this is just like [`export`].
+pub fn export_lines(
+    mut writer: impl Write,
+    repo: &Repo,
+    info: Option<RepoInfo>,
+    load_attachment: impl FnMut(&str) -> anyhow::Result<Vec<u8>>,
+) -> anyhow::Result<()> {
+    serde_json::to_writer(&mut writer, &Header::new(info))?;
+    writeln!(writer)?;
+    for note in dump_notes(repo, load_attachment)? {
+        serde_json::to_writer(&mut writer, &note)?;
+        writeln!(writer)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,130 @@
+use std::{fmt::Write, fs, io::ErrorKind, path::Path};
+
+use anyhow::{Context, bail};
+
+use crate::{ids::NoteId, store::Store};
+
+use super::Walk;
+
+pub const INDEX_FILE: &str = "index.md";
+
+pub fn note_file(id: NoteId) -> String {
+    format!("{id}.md")
+}
+
+/// Escape characters that would otherwise be interpreted as link syntax.
+fn escape_link_text(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        if matches!(c, '[' | ']' | '\\') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    result
+}
+
+fn link(store: &Store, id: NoteId) -> String {
+    let text = store
+        .get(id)
+        .and_then(|note| note.text.lines().next().map(|it| it.trim().to_string()))
+        .filter(|it| !it.is_empty())
+        .unwrap_or_else(|| id.to_string());
+
+    format!("[{}]({})", escape_link_text(&text), note_file(id))
+}
+
+fn render_note(store: &Store, id: NoteId) -> String {
+    let note = store.get(id).expect("note must exist");
+
+    let mut parents = note.parents.into_iter().collect::<Vec<_>>();
+    parents.sort_unstable();
+
+    let mut sections = vec![];
+
+    let text = note.text.trim_end();
+    if !text.is_empty() {
+        sections.push(format!("{text}\n"));
+    }
+
+    if !note.children.is_empty() {
+        let mut section = String::from("## Children\n\n");
+        for child in note.children {
+            writeln!(section, "- {}", link(store, child)).unwrap();
+        }
+        sections.push(section);
+    }
+
+    if !parents.is_empty() {
+        let mut section = String::from("## Parents\n\n");
+        for parent in parents {
+            writeln!(section, "- {}", link(store, parent)).unwrap();
+        }
+        sections.push(section);
+    }
+
+    sections.join("\n")
+}
+
+fn render_index(store: &Store, walk: &Walk) -> String {
+    let roots = store.roots();
+    let cycles = walk
+        .starts
+        .iter()
+        .filter(|id| !roots.contains(id))
+        .collect::<Vec<_>>();
+
+    let mut result = String::new();
+    writeln!(result, "# {}", crate::PROPER_NAME).unwrap();
+
+    if !roots.is_empty() {
+        writeln!(result).unwrap();
+        for id in roots {
+            writeln!(result, "- {}", link(store, id)).unwrap();
+        }
+    }
+
+    if !cycles.is_empty() {
+        writeln!(result, "\n## Cycles\n").unwrap();
+        writeln!(result, "Notes not reachable from any note listed above.\n").unwrap();
+        for id in cycles {
+            writeln!(result, "- {}", link(store, *id)).unwrap();
+        }
+    }
+
+    result
+}
+
+/// Export all notes of a store as Markdown files, one file per note.
+///
+/// Each note links to its children and parents by id. An additional index
+/// file links to all notes without parents, as well as to notes that can only
+/// be reached via cycles.
+///
+/// The target directory must either not exist or be empty. Returns the number
+/// of notes exported.
+pub fn export(store: &Store, dir: &Path) -> anyhow::Result<usize> {
+    match fs::read_dir(dir) {
+        Ok(mut entries) => {
+            if entries.next().is_some() {
+                bail!("{} is not empty", dir.display());
+            }
+        }
+        Err(err) if err.kind() == ErrorKind::NotFound => fs::create_dir_all(dir)?,
+        Err(err) => Err(err)?,
+    }
+
+    let walk = Walk::new(store);
+
+    let path = dir.join(INDEX_FILE);
+    fs::write(&path, render_index(store, &walk))
+        .with_context(|| format!("failed to write {}", path.display()))?;
+
+    for id in &walk.order {
+        let path = dir.join(note_file(*id));
+        fs::write(&path, render_note(store, *id))
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    Ok(walk.order.len())
+}
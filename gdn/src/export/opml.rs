@@ -0,0 +1,118 @@
+use std::{collections::HashSet, fmt::Write};
+
+use anyhow::anyhow;
+
+use crate::{ids::NoteId, store::Store};
+
+use super::Walk;
+
+/// The outline attribute holding the [`NoteId`] of the exported note.
+pub const ID_ATTRIBUTE: &str = "gdnId";
+
+/// Escape a string for use inside a double-quoted XML attribute.
+///
+/// Whitespace other than spaces is escaped as well because XML parsers
+/// normalize it to spaces otherwise, which would make multi-line notes lose
+/// their line breaks.
+fn escape_attribute(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\n' => result.push_str("&#10;"),
+            '\r' => result.push_str("&#13;"),
+            '\t' => result.push_str("&#9;"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+struct Exporter<'a> {
+    store: &'a Store,
+    visited: HashSet<NoteId>,
+    result: String,
+}
+
+impl Exporter<'_> {
+    fn write_outline(&mut self, id: NoteId, depth: usize) {
+        let Some(note) = self.store.get(id) else {
+            return;
+        };
+
+        let indent = "  ".repeat(depth);
+        let text = escape_attribute(&note.text);
+        write!(
+            self.result,
+            r#"{indent}<outline text="{text}" {ID_ATTRIBUTE}="{id}""#
+        )
+        .unwrap();
+
+        // A note that was already exported is only referenced by its id. This
+        // keeps the output finite in the presence of cycles, and ensures that
+        // notes with multiple parents are imported as a single note again.
+        let first = self.visited.insert(id);
+        if !first || note.children.is_empty() {
+            writeln!(self.result, "/>").unwrap();
+            return;
+        }
+
+        writeln!(self.result, ">").unwrap();
+        for child in note.children {
+            self.write_outline(child, depth + 1);
+        }
+        writeln!(self.result, "{indent}</outline>").unwrap();
+    }
+}
+
+fn export_outlines(store: &Store, title: &str, roots: &[NoteId]) -> String {
+    let mut exporter = Exporter {
+        store,
+        visited: HashSet::new(),
+        result: String::new(),
+    };
+
+    let title = escape_attribute(title);
+    let result = &mut exporter.result;
+    writeln!(result, r#"<?xml version="1.0" encoding="UTF-8"?>"#).unwrap();
+    writeln!(result, r#"<opml version="2.0">"#).unwrap();
+    writeln!(result, "  <head>").unwrap();
+    writeln!(result, "    <title>{title}</title>").unwrap();
+    writeln!(result, "  </head>").unwrap();
+    writeln!(result, "  <body>").unwrap();
+
+    for root in roots {
+        exporter.write_outline(*root, 2);
+    }
+
+    let result = &mut exporter.result;
+    writeln!(result, "  </body>").unwrap();
+    writeln!(result, "</opml>").unwrap();
+
+    exporter.result
+}
+
+/// Export a note and all its descendants as an OPML outline.
+///
+/// Every outline carries the id of its note in the [`ID_ATTRIBUTE`]
+/// attribute. Notes that appear more than once are only expanded the first
+/// time, later occurrences have no children.
+pub fn export(store: &Store, root: NoteId) -> anyhow::Result<String> {
+    let note = store
+        .get(root)
+        .ok_or_else(|| anyhow!("no note with id {root}"))?;
+    let title = note.text.lines().next().unwrap_or_default();
+    Ok(export_outlines(store, title, &[root]))
+}
+
+/// Export all notes of a store as an OPML outline.
+///
+/// The top-level outlines are the store's roots, followed by one note per
+/// cycle that isn't reachable from any root. See [`export`] for more details.
+pub fn export_all(store: &Store) -> String {
+    let walk = Walk::new(store);
+    export_outlines(store, crate::PROPER_NAME, &walk.starts)
+}
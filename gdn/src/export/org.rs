@@ -0,0 +1,107 @@
+use std::{collections::HashSet, fmt::Write};
+
+use anyhow::anyhow;
+
+use crate::{ids::NoteId, store::Store};
+
+use super::Walk;
+
+/// The property holding the [`NoteId`] of the exported note.
+pub const ID_PROPERTY: &str = "ID";
+
+/// The property holding the creation time of the exported note.
+pub const CREATED_PROPERTY: &str = "CREATED";
+
+/// Escape a body line so it isn't mistaken for a heading.
+///
+/// Lines starting with `*` or `,` are prefixed with a `,`, similar to how Org
+/// escapes lines in source blocks.
+pub fn escape_line(line: &str) -> String {
+    if line.starts_with(['*', ',']) {
+        format!(",{line}")
+    } else {
+        line.to_string()
+    }
+}
+
+struct Exporter<'a> {
+    store: &'a Store,
+    visited: HashSet<NoteId>,
+    result: String,
+}
+
+impl Exporter<'_> {
+    fn write_heading(&mut self, id: NoteId, level: usize) {
+        let Some(note) = self.store.get(id) else {
+            return;
+        };
+
+        // Unlike `lines`, this keeps trailing empty lines, which the importer
+        // keeps as well.
+        let mut lines = note.text.split('\n');
+        let title = lines.next().unwrap_or_default();
+        let created = id.time_utc().strftime("%Y-%m-%d %a %H:%M");
+
+        let stars = "*".repeat(level);
+        writeln!(self.result, "{stars} {title}").unwrap();
+        writeln!(self.result, ":PROPERTIES:").unwrap();
+        writeln!(self.result, ":{ID_PROPERTY}: {id}").unwrap();
+        writeln!(self.result, ":{CREATED_PROPERTY}: [{created}]").unwrap();
+        writeln!(self.result, ":END:").unwrap();
+
+        // A note that was already exported is only referenced by its heading
+        // and id. This keeps the output finite in the presence of cycles, and
+        // ensures that notes with multiple parents are imported as a single
+        // note again.
+        if !self.visited.insert(id) {
+            return;
+        }
+
+        for line in lines {
+            writeln!(self.result, "{}", escape_line(line)).unwrap();
+        }
+
+        for child in note.children {
+            self.write_heading(child, level + 1);
+        }
+    }
+}
+
+fn export_headings(store: &Store, title: &str, roots: &[NoteId]) -> String {
+    let mut exporter = Exporter {
+        store,
+        visited: HashSet::new(),
+        result: String::new(),
+    };
+
+    writeln!(exporter.result, "#+TITLE: {title}").unwrap();
+    for root in roots {
+        exporter.write_heading(*root, 1);
+    }
+
+    exporter.result
+}
+
+/// Export a note and all its descendants as an Org document.
+///
+/// Every note becomes a heading whose title is the first line of the note's
+/// text, followed by a property drawer with the note's id and creation time
+/// and the remaining lines of text. Children become nested headings. Notes
+/// that appear more than once are only expanded the first time, later
+/// occurrences have neither text nor children.
+pub fn export(store: &Store, root: NoteId) -> anyhow::Result<String> {
+    let note = store
+        .get(root)
+        .ok_or_else(|| anyhow!("no note with id {root}"))?;
+    let title = note.text.lines().next().unwrap_or_default();
+    Ok(export_headings(store, title, &[root]))
+}
+
+/// Export all notes of a store as an Org document.
+///
+/// The top-level headings are the store's roots, followed by one note per
+/// cycle that isn't reachable from any root. See [`export`] for more details.
+pub fn export_all(store: &Store) -> String {
+    let walk = Walk::new(store);
+    export_headings(store, crate::PROPER_NAME, &walk.starts)
+}
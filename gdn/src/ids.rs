@@ -63,6 +63,20 @@ impl NoteId {
         Self(TimestampId::new())
     }
 
+    /// Create a new id that is not in use yet.
+    ///
+    /// Ids created during the same second only differ in their random part, so
+    /// they collide occasionally when many notes are created at once. This
+    /// keeps creating ids until `is_used` returns false.
+    pub fn new_unused(mut is_used: impl FnMut(Self) -> bool) -> Self {
+        loop {
+            let id = Self::new();
+            if !is_used(id) {
+                return id;
+            }
+        }
+    }
+
     pub fn timestamp(self) -> Timestamp {
         self.0.timestamp()
     }
@@ -152,3 +166,73 @@ impl<'de> Deserialize<'de> for RepoId {
             .map_err(|()| serde::de::Error::custom("invalid repo id"))
     }
 }
+
+/// The minimum length of abbreviated ids, including their letter, see
+/// [`unique_prefix_len`].
+pub const MIN_PREFIX_LEN: usize = 8;
+
+/// Ids that can be referred to by a prefix, see [`matching`].
+pub trait Prefixed: Copy + Ord + fmt::Display {
+    /// The letter every id starts with.
+    const LETTER: char;
+}
+
+impl Prefixed for NoteId {
+    const LETTER: char = 'n';
+}
+
+impl Prefixed for RepoId {
+    const LETTER: char = 'r';
+}
+
+/// Bring a prefix into the form ids are displayed in, or return [`None`] if
+/// no id can start with it.
+fn normalize_prefix<T: Prefixed>(prefix: &str) -> Option<String> {
+    let hex = match prefix.chars().next() {
+        Some(c) if c.eq_ignore_ascii_case(&T::LETTER) => &prefix[1..],
+        _ => prefix,
+    };
+    if !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("{}{}", T::LETTER, hex.to_ascii_uppercase()))
+}
+
+/// The ids starting with a prefix, sorted.
+///
+/// The prefix is case-insensitive and may omit the letter ids start with, so
+/// `n006ad5` and `006AD5` both match `n006AD5C2321BF778`. A full id only
+/// matches itself.
+pub fn matching<T: Prefixed>(ids: impl IntoIterator<Item = T>, prefix: &str) -> Vec<T> {
+    let Some(prefix) = normalize_prefix::<T>(prefix) else {
+        return vec![];
+    };
+    let mut result = ids
+        .into_iter()
+        .filter(|id| id.to_string().starts_with(&prefix))
+        .collect::<Vec<_>>();
+    result.sort_unstable();
+    result.dedup();
+    result
+}
+
+/// The length of the shortest prefix that tells all of the ids apart, like
+/// the abbreviated commit hashes of git.
+///
+/// The length is at least [`MIN_PREFIX_LEN`]. Since ids start with their
+/// creation time, ids created around the same time need longer prefixes.
+pub fn unique_prefix_len<T: Prefixed>(ids: impl IntoIterator<Item = T>) -> usize {
+    let mut ids = ids.into_iter().map(|it| it.to_string()).collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.dedup();
+    ids.windows(2)
+        .map(|pair| {
+            let common = pair[0]
+                .bytes()
+                .zip(pair[1].bytes())
+                .take_while(|(a, b)| a == b)
+                .count();
+            common + 1
+        })
+        .fold(MIN_PREFIX_LEN, usize::max)
+}
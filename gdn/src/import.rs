@@ -0,0 +1,205 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+};
+
+use crate::{
+    ids::NoteId,
+    repo::{Metadata, Note, Repo},
+};
+
+pub mod json;
+pub mod markdown;
+pub mod opml;
+pub mod org;
+
+/// The result of importing a hierarchical outline.
+pub struct Outline {
+    /// The notes of the top-level outline entries, in order.
+    pub roots: Vec<NoteId>,
+    /// All notes defined by the outline, in order of appearance.
+    pub notes: Vec<Note>,
+}
+
+/// Build notes from nested outline entries.
+///
+/// If a note id appears multiple times, the first occurrence defines the
+/// note's text and children while all later ones only reference it. Entries
+/// nested inside such references are ignored. Entries without an id receive a
+/// fresh one that neither appears in the outline nor is already taken.
+pub(crate) struct OutlineBuilder<'a> {
+    /// Ids already used by the repo the notes are imported into.
+    taken: &'a HashSet<NoteId>,
+    roots: Vec<NoteId>,
+    notes: HashMap<NoteId, Note>,
+    order: Vec<NoteId>,
+    /// For every open outline, the id of its note if it is the note's first
+    /// occurrence, or [`None`] if it only references an earlier occurrence.
+    stack: Vec<Option<NoteId>>,
+    /// The ids created for entries without an id.
+    fresh: HashSet<NoteId>,
+}
+
+impl<'a> OutlineBuilder<'a> {
+    pub fn new(taken: &'a HashSet<NoteId>) -> Self {
+        Self {
+            taken,
+            roots: vec![],
+            notes: HashMap::new(),
+            order: vec![],
+            stack: vec![],
+            fresh: HashSet::new(),
+        }
+    }
+
+    fn new_id(&self) -> NoteId {
+        NoteId::new_unused(|id| self.taken.contains(&id) || self.notes.contains_key(&id))
+    }
+
+    /// Give the note of an entry without an id a different fresh id, because
+    /// a later entry turned out to use the same id.
+    fn replace_fresh(&mut self, old: NoteId) {
+        let new = self.new_id();
+        let replace = |id: &mut NoteId| {
+            if *id == old {
+                *id = new;
+            }
+        };
+
+        let mut note = self.notes.remove(&old).unwrap();
+        note.id = new;
+        self.notes.insert(new, note);
+        self.fresh.insert(new);
+
+        self.roots.iter_mut().for_each(replace);
+        self.order.iter_mut().for_each(replace);
+        self.stack.iter_mut().flatten().for_each(replace);
+        for note in self.notes.values_mut() {
+            note.children.iter_mut().for_each(replace);
+        }
+    }
+
+    /// Start a new outline nested inside the currently open outline.
+    ///
+    /// If the entry has no id, a fresh one is created.
+    pub fn open(&mut self, id: Option<NoteId>, text: String) {
+        // The children of references are ignored. Only the first occurrence of
+        // a note determines its text and children.
+        if let Some(None) = self.stack.last() {
+            self.stack.push(None);
+            return;
+        }
+
+        let id = match id {
+            Some(id) => {
+                if self.fresh.remove(&id) {
+                    self.replace_fresh(id);
+                }
+                id
+            }
+            None => {
+                let id = self.new_id();
+                self.fresh.insert(id);
+                id
+            }
+        };
+
+        match self.stack.last().copied().flatten() {
+            None => self.roots.push(id),
+            Some(parent) => self.notes.get_mut(&parent).unwrap().children.push(id),
+        }
+
+        if self.notes.contains_key(&id) {
+            self.stack.push(None);
+            return;
+        }
+
+        let note = Note {
+            id,
+            text,
+            children: vec![],
+            meta: Metadata::default(),
+        };
+        self.notes.insert(id, note);
+        self.order.push(id);
+        self.stack.push(Some(id));
+    }
+
+    /// Close the currently open outline.
+    pub fn close(&mut self) {
+        self.stack.pop();
+    }
+
+    /// Whether all outlines have been closed.
+    pub fn is_done(&self) -> bool {
+        self.stack.is_empty()
+    }
+
+    pub fn finish(mut self) -> Outline {
+        let notes = self
+            .order
+            .into_iter()
+            .map(|id| self.notes.remove(&id).unwrap())
+            .collect();
+
+        Outline {
+            roots: self.roots,
+            notes,
+        }
+    }
+}
+
+/// How many notes [`merge`] added to or updated in a repo.
+pub struct MergeSummary {
+    pub added: usize,
+    pub updated: usize,
+    /// How many notes were put in the trash. Only dumps contain such notes,
+    /// see [`json::merge`].
+    pub trashed: usize,
+}
+
+/// Merge imported notes into a repo.
+///
+/// Notes whose id already exists in the repo replace the existing note, all
+/// other notes are added. Metadata missing from a replacing note is taken from
+/// the note it replaces. Notes with the same id as a note in the trash replace
+/// that note too, taking it out of the trash.
+pub fn merge(repo: &mut Repo, mut notes: Vec<Note>) -> MergeSummary {
+    let ids = notes.iter().map(|note| note.id).collect::<HashSet<_>>();
+    repo.trash.retain(|trashed| !ids.contains(&trashed.note.id));
+
+    let before = repo.notes.len();
+    let mut replaced = HashMap::new();
+    repo.notes.retain_mut(|note| {
+        if ids.contains(&note.id) {
+            replaced.insert(note.id, mem::take(&mut note.meta));
+            false
+        } else {
+            true
+        }
+    });
+    let updated = before - repo.notes.len();
+
+    for note in &mut notes {
+        let Some(old) = replaced.remove(&note.id) else {
+            continue;
+        };
+        let meta = &mut note.meta;
+        meta.modified = meta.modified.or(old.modified);
+        meta.device = meta.device.take().or(old.device);
+        if meta.properties.is_empty() {
+            meta.properties = old.properties;
+        }
+        if meta.attachments.is_empty() {
+            meta.attachments = old.attachments;
+        }
+    }
+
+    repo.notes.extend(notes);
+
+    MergeSummary {
+        added: ids.len() - updated,
+        updated,
+        trashed: 0,
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, bail};
+use serde::{Deserialize, de::IgnoredAny};
+
+use super::MergeSummary;
+use crate::{
+    export::json::{Dump, DumpNote, Header, VERSION},
+    repo::{Repo, TrashedNote},
+};
+
+fn check_header(header: &Header) -> anyhow::Result<()> {
+    if header.format != crate::TECHNICAL_NAME {
+        bail!("not a {} dump", crate::PROPER_NAME);
+    }
+    if !(1..=VERSION).contains(&header.version) {
+        bail!(
+            "unsupported dump version {} (expected at most {VERSION})",
+            header.version
+        );
+    }
+    Ok(())
+}
+
+fn import_lines(json: &str) -> anyhow::Result<Dump> {
+    let mut lines = json
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty());
+
+    let Some((_, header)) = lines.next() else {
+        bail!("empty dump");
+    };
+    let header = serde_json::from_str::<Header>(header).context("failed to parse dump header")?;
+    check_header(&header)?;
+
+    let notes = lines
+        .map(|(i, line)| {
+            serde_json::from_str::<DumpNote>(line)
+                .with_context(|| format!("failed to parse note on line {}", i + 1))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(Dump { header, notes })
+}
+
+/// Read a dump created by [`crate::export::json::export`] or
+/// [`crate::export::json::export_lines`].
+///
+/// The format is detected automatically. No notes are modified or saved, that
+/// is up to the caller, usually via [`super::merge`].
+pub fn import(json: &str) -> anyhow::Result<Dump> {
+    /// The part of the first JSON value that tells the formats apart.
+    #[derive(Deserialize)]
+    struct Probe {
+        notes: Option<IgnoredAny>,
+    }
+
+    // Both formats start with an object, but only a single document contains
+    // the notes. In a JSON lines stream, the first object is just the header.
+    let probe = serde_json::Deserializer::from_str(json)
+        .into_iter::<Probe>()
+        .next()
+        .context("empty dump")?
+        .context("failed to parse dump")?;
+    if probe.notes.is_none() {
+        return import_lines(json);
+    }
+
+    let dump = serde_json::from_str::<Dump>(json).context("failed to parse dump")?;
+    check_header(&dump.header)?;
+    Ok(dump)
+}
+
+/// Merge the notes of a dump into a repo.
+///
+/// Live notes are merged like in [`super::merge`], along with their reviews.
+/// Trashed notes are put in the trash, replacing trashed notes with the same
+/// id, unless the repo has a live note with their id. The content of
+/// attachments is stored via `save_attachment`, which returns its hash, see
+/// [`crate::data::save_attachment`].
+pub fn merge(
+    repo: &mut Repo,
+    notes: Vec<DumpNote>,
+    mut save_attachment: impl FnMut(&[u8]) -> anyhow::Result<String>,
+) -> anyhow::Result<MergeSummary> {
+    let mut live = vec![];
+    let mut reviews = vec![];
+    let mut trash = vec![];
+    for note in notes {
+        match note.into_parts(&mut save_attachment)? {
+            (note, review, None) => {
+                if let Some(review) = review {
+                    reviews.push((note.id, review));
+                }
+                live.push(note);
+            }
+            (note, review, Some(trashed)) => trash.push(TrashedNote {
+                deleted: trashed.deleted,
+                parents: trashed.parents,
+                note,
+                review,
+            }),
+        }
+    }
+
+    let mut summary = super::merge(repo, live);
+    repo.reviews.extend(reviews);
+
+    let live = repo
+        .notes
+        .iter()
+        .map(|note| note.id)
+        .collect::<HashSet<_>>();
+    trash.retain(|trashed| !live.contains(&trashed.note.id));
+    let trashed = trash
+        .iter()
+        .map(|trashed| trashed.note.id)
+        .collect::<HashSet<_>>();
+    repo.trash.retain(|it| !trashed.contains(&it.note.id));
+    summary.trashed = trash.len();
+    repo.trash.extend(trash);
+
+    Ok(summary)
+}
@@ -0,0 +1,416 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::Context;
+
+use crate::{
+    ids::NoteId,
+    repo::{Metadata, Note},
+};
+
+struct PendingNote {
+    text: String,
+    children: Vec<NoteId>,
+}
+
+/// An unresolved `[[wikilink]]`.
+pub struct UnresolvedLink {
+    pub file: PathBuf,
+    pub target: String,
+}
+
+/// The result of importing a directory.
+pub struct Import {
+    /// The note representing the imported directory itself.
+    pub root: NoteId,
+    /// All newly created notes, including the root note.
+    pub notes: Vec<Note>,
+    /// The number of Markdown files that were imported.
+    pub files: usize,
+    /// Links whose target couldn't be found. They are kept as text.
+    pub unresolved: Vec<UnresolvedLink>,
+}
+
+/// Normalize a link target or file path so it can be used for lookups.
+///
+/// Obsidian ignores case and the `.md` extension when resolving links.
+fn normalize_name(name: &str) -> String {
+    let name = name.trim().replace('\\', "/").to_lowercase();
+    match name.strip_suffix(".md") {
+        Some(name) => name.to_string(),
+        None => name,
+    }
+}
+
+/// Find all `[[wikilinks]]` in a piece of text and return their targets.
+///
+/// Aliases (`[[target|alias]]`) and heading or block references
+/// (`[[target#heading]]`) are stripped, leaving only the target note.
+fn find_wikilinks(text: &str) -> Vec<String> {
+    let mut result = vec![];
+    let mut rest = text;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("]]") else { break };
+        let inner = &rest[..end];
+        rest = &rest[end + 2..];
+
+        let target = inner.split(['|', '#', '^']).next().unwrap_or_default();
+        if !target.trim().is_empty() {
+            result.push(normalize_name(target));
+        }
+    }
+    result
+}
+
+/// If the text consists of nothing but a single `[[wikilink]]` (or embed),
+/// return its target.
+fn single_wikilink(text: &str) -> Option<String> {
+    let text = text.trim();
+    let text = text.strip_prefix('!').unwrap_or(text);
+    let inner = text.strip_prefix("[[")?.strip_suffix("]]")?;
+    if inner.contains("[[") || inner.contains("]]") {
+        return None;
+    }
+    let links = find_wikilinks(text);
+    match links.as_slice() {
+        [link] => Some(link.clone()),
+        _ => None,
+    }
+}
+
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+    Some((level, rest.trim()))
+}
+
+/// Width of the indentation of a line, with tabs counting as four spaces.
+fn indentation(line: &str) -> usize {
+    line.chars()
+        .take_while(|c| c.is_whitespace())
+        .map(|c| if c == '\t' { 4 } else { 1 })
+        .sum()
+}
+
+fn parse_list_item(line: &str) -> Option<(usize, &str)> {
+    let indent = indentation(line);
+    let trimmed = line.trim_start();
+
+    let rest = if let Some(rest) = trimmed.strip_prefix(['-', '*', '+']) {
+        rest
+    } else {
+        let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+        if digits == 0 {
+            return None;
+        }
+        trimmed[digits..].strip_prefix(['.', ')'])?
+    };
+
+    if rest.is_empty() {
+        return Some((indent, ""));
+    }
+    if !rest.starts_with([' ', '\t']) {
+        return None;
+    }
+
+    // Task list items are common in vaults, so strip their checkbox.
+    let rest = rest.trim();
+    let rest = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|prefix| rest.strip_prefix(prefix))
+        .unwrap_or(rest);
+
+    Some((indent, rest))
+}
+
+fn is_fence(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("```") || line.starts_with("~~~")
+}
+
+struct Importer<'a> {
+    /// Ids already used by the repo the notes are imported into.
+    taken: &'a HashSet<NoteId>,
+    notes: HashMap<NoteId, PendingNote>,
+    order: Vec<NoteId>,
+    files: HashMap<String, NoteId>,
+    current_file: PathBuf,
+    unresolved: Vec<UnresolvedLink>,
+}
+
+impl<'a> Importer<'a> {
+    fn new(taken: &'a HashSet<NoteId>) -> Self {
+        Self {
+            taken,
+            notes: HashMap::new(),
+            order: vec![],
+            files: HashMap::new(),
+            current_file: PathBuf::new(),
+            unresolved: vec![],
+        }
+    }
+
+    fn create(&mut self, text: String) -> NoteId {
+        let id = NoteId::new_unused(|id| self.taken.contains(&id) || self.notes.contains_key(&id));
+        let note = PendingNote {
+            text,
+            children: vec![],
+        };
+        self.notes.insert(id, note);
+        self.order.push(id);
+        id
+    }
+
+    fn add_child(&mut self, parent: NoteId, child: NoteId) {
+        self.notes.get_mut(&parent).unwrap().children.push(child);
+    }
+
+    /// Add the targets of all links in `text` as children of `id`.
+    fn add_links(&mut self, id: NoteId, text: &str) {
+        for target in find_wikilinks(text) {
+            match self.files.get(&target) {
+                Some(child) => self.add_child(id, *child),
+                None => self.unresolved.push(UnresolvedLink {
+                    file: self.current_file.clone(),
+                    target,
+                }),
+            }
+        }
+    }
+
+    /// Add a block of text as a child of `parent`.
+    ///
+    /// If the block is just a link to another file, that file's note is added
+    /// directly instead of creating a new note.
+    fn add_block(&mut self, parent: NoteId, text: String) -> Option<NoteId> {
+        if let Some(target) = single_wikilink(&text)
+            && let Some(child) = self.files.get(&target)
+        {
+            self.add_child(parent, *child);
+            return None;
+        }
+
+        let id = self.create(text.clone());
+        self.add_links(id, &text);
+        self.add_child(parent, id);
+        Some(id)
+    }
+
+    fn collect_dir(
+        &mut self,
+        base: &Path,
+        dir: &Path,
+        parent: NoteId,
+        files: &mut Vec<(PathBuf, NoteId)>,
+    ) -> anyhow::Result<()> {
+        let mut entries = fs::read_dir(dir)
+            .with_context(|| format!("failed to read {}", dir.display()))?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        entries.sort_unstable();
+
+        for path in entries {
+            let Some(name) = path.file_name().and_then(|it| it.to_str()) else {
+                continue;
+            };
+
+            // Skip hidden files and dirs, like `.obsidian` or `.git`.
+            if name.starts_with('.') {
+                continue;
+            }
+
+            if path.is_dir() {
+                let id = self.create(name.to_string());
+                self.collect_dir(base, &path, id, files)?;
+
+                // Skip dirs without any Markdown files, e.g. attachment dirs.
+                if self.notes[&id].children.is_empty() {
+                    self.notes.remove(&id);
+                    self.order.retain(|it| *it != id);
+                } else {
+                    self.add_child(parent, id);
+                }
+            } else if let Some(stem) = name.strip_suffix(".md") {
+                let id = self.create(stem.to_string());
+                self.add_child(parent, id);
+
+                // Links may refer to files by their name or by their path
+                // relative to the vault. If multiple files share a name, the
+                // first one wins.
+                let relative = path.strip_prefix(base)?.to_string_lossy();
+                self.files.insert(normalize_name(&relative), id);
+                self.files.entry(normalize_name(stem)).or_insert(id);
+
+                files.push((path, id));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn parse_file(&mut self, file_note: NoteId, content: &str) {
+        // Stack of (heading level, note). The file itself acts as level 0.
+        let mut headings = vec![(0, file_note)];
+        // Stack of (indentation, note) for nested list items.
+        let mut items: Vec<(usize, NoteId)> = vec![];
+        let mut paragraph: Vec<&str> = vec![];
+        let mut in_fence = false;
+
+        let mut lines = content.lines().peekable();
+
+        // Skip YAML front matter.
+        if lines.peek().is_some_and(|line| line.trim_end() == "---") {
+            lines.next();
+            for line in lines.by_ref() {
+                if line.trim_end() == "---" {
+                    break;
+                }
+            }
+        }
+
+        for line in lines {
+            if in_fence {
+                paragraph.push(line);
+                if is_fence(line) {
+                    in_fence = false;
+                }
+                continue;
+            }
+
+            let parent = headings.last().unwrap().1;
+
+            if is_fence(line) {
+                items.clear();
+                paragraph.push(line);
+                in_fence = true;
+            } else if line.trim().is_empty() {
+                self.flush_paragraph(parent, &mut paragraph);
+            } else if let Some((level, text)) = parse_heading(line) {
+                self.flush_paragraph(parent, &mut paragraph);
+                items.clear();
+                while headings.last().unwrap().0 >= level {
+                    headings.pop();
+                }
+                let parent = headings.last().unwrap().1;
+
+                // Files often start with a heading repeating their name, which
+                // would otherwise result in two nested notes with equal text.
+                if parent == file_note
+                    && self.notes[&file_note].children.is_empty()
+                    && text.eq_ignore_ascii_case(&self.notes[&file_note].text)
+                {
+                    headings.push((level, file_note));
+                    continue;
+                }
+
+                let id = self.create(text.to_string());
+                self.add_child(parent, id);
+                headings.push((level, id));
+            } else if let Some((indent, text)) = parse_list_item(line) {
+                self.flush_paragraph(parent, &mut paragraph);
+                while items.last().is_some_and(|(i, _)| *i >= indent) {
+                    items.pop();
+                }
+                let parent = items.last().map(|(_, id)| *id).unwrap_or(parent);
+                if let Some(id) = self.add_block(parent, text.to_string()) {
+                    items.push((indent, id));
+                }
+            } else if indentation(line) > 0
+                && paragraph.is_empty()
+                && let Some((_, id)) = items.last()
+            {
+                // Continuation of the previous list item.
+                let id = *id;
+                let note = self.notes.get_mut(&id).unwrap();
+                note.text.push('\n');
+                note.text.push_str(line.trim());
+                self.add_links(id, line);
+            } else {
+                items.clear();
+                paragraph.push(line);
+            }
+        }
+
+        let parent = headings.last().unwrap().1;
+        self.flush_paragraph(parent, &mut paragraph);
+    }
+
+    fn flush_paragraph(&mut self, parent: NoteId, paragraph: &mut Vec<&str>) {
+        if paragraph.is_empty() {
+            return;
+        }
+        let text = paragraph.join("\n");
+        paragraph.clear();
+        self.add_block(parent, text);
+    }
+
+    fn finish(mut self, root: NoteId, files: usize) -> Import {
+        let notes = self
+            .order
+            .into_iter()
+            .map(|id| {
+                let note = self.notes.remove(&id).unwrap();
+                Note {
+                    id,
+                    text: note.text,
+                    children: note.children,
+                    meta: Metadata::default(),
+                }
+            })
+            .collect();
+
+        Import {
+            root,
+            notes,
+            files,
+            unresolved: self.unresolved,
+        }
+    }
+}
+
+/// Import a directory of Markdown files, like an Obsidian vault.
+///
+/// The directory, every subdirectory and every Markdown file become notes
+/// whose children are their contents. Within a file, headings, list items and
+/// paragraphs become notes, nested according to heading level and list
+/// indentation. A `[[wikilink]]` to another file adds that file's note as a
+/// child of the note containing the link.
+///
+/// All notes receive fresh ids that are not in `taken`, which should contain
+/// the ids of the repo the notes are imported into. No notes are modified or
+/// saved, that is up to the caller.
+pub fn import(dir: &Path, taken: &HashSet<NoteId>) -> anyhow::Result<Import> {
+    let mut importer = Importer::new(taken);
+
+    let name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|it| Some(it.file_name()?.to_string_lossy().to_string()))
+        .unwrap_or_else(|| dir.display().to_string());
+    let root = importer.create(name);
+
+    // Create the notes for all files before parsing any of them so links can
+    // be resolved regardless of the order in which files are parsed.
+    let mut files = vec![];
+    importer.collect_dir(dir, dir, root, &mut files)?;
+    let file_count = files.len();
+
+    for (path, id) in files {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        importer.current_file = path;
+        importer.parse_file(id, &content);
+    }
+
+    Ok(importer.finish(root, file_count))
+}
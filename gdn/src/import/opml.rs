@@ -0,0 +1,91 @@
+use std::collections::HashSet;
+
+use anyhow::{Context, anyhow, bail};
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+use crate::{export::opml::ID_ATTRIBUTE, ids::NoteId};
+
+use super::{Outline, OutlineBuilder};
+
+fn parse_outline(element: &BytesStart<'_>) -> anyhow::Result<(Option<NoteId>, String)> {
+    let mut id = None;
+    let mut text = String::new();
+    let mut note = None;
+
+    for attribute in element.attributes() {
+        let attribute = attribute?;
+        let value = attribute.unescape_value()?;
+        match attribute.key.local_name().as_ref() {
+            b"text" => text = value.into_owned(),
+            b"_note" => note = Some(value.into_owned()),
+            key if key == ID_ATTRIBUTE.as_bytes() => {
+                let parsed = value
+                    .parse()
+                    .map_err(|()| anyhow!("invalid note id {value:?}"))?;
+                id = Some(parsed);
+            }
+            _ => {}
+        }
+    }
+
+    // Outliners like Workflowy and Dynalist store additional text in a
+    // separate `_note` attribute.
+    if let Some(note) = note.filter(|it| !it.is_empty()) {
+        text.push('\n');
+        text.push_str(&note);
+    }
+
+    Ok((id, text))
+}
+
+/// Import an OPML document.
+///
+/// Every outline becomes a note with its nested outlines as ordered children.
+/// Outlines carrying a note id in the [`ID_ATTRIBUTE`] attribute keep that id,
+/// so documents produced by [`crate::export::opml`] can be imported again
+/// without loss. If an id appears multiple times, the first occurrence
+/// defines the note and all later ones only reference it. All other outlines
+/// receive fresh ids that are not in `taken`, which should contain the ids of
+/// the repo the notes are imported into.
+///
+/// No notes are modified or saved, that is up to the caller.
+pub fn import(opml: &str, taken: &HashSet<NoteId>) -> anyhow::Result<Outline> {
+    let mut reader = Reader::from_str(opml);
+    let mut builder = OutlineBuilder::new(taken);
+    let mut in_body = false;
+
+    loop {
+        let position = reader.buffer_position();
+        let event = reader
+            .read_event()
+            .with_context(|| format!("failed to parse OPML at byte {position}"))?;
+
+        match event {
+            Event::Start(element) if element.local_name().as_ref() == b"body" => in_body = true,
+            Event::End(element) if element.local_name().as_ref() == b"body" => in_body = false,
+            Event::Start(element) if in_body && element.local_name().as_ref() == b"outline" => {
+                let (id, text) = parse_outline(&element)?;
+                builder.open(id, text);
+            }
+            Event::Empty(element) if in_body && element.local_name().as_ref() == b"outline" => {
+                let (id, text) = parse_outline(&element)?;
+                builder.open(id, text);
+                builder.close();
+            }
+            Event::End(element) if in_body && element.local_name().as_ref() == b"outline" => {
+                builder.close();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    if !builder.is_done() {
+        bail!("unexpected end of OPML document");
+    }
+
+    Ok(builder.finish())
+}
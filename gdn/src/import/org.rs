@@ -0,0 +1,132 @@
+use std::collections::HashSet;
+
+use crate::{export::org::ID_PROPERTY, ids::NoteId};
+
+use super::{Outline, OutlineBuilder};
+
+/// Split a heading into its level and title.
+///
+/// Only the space after the stars is removed, since that is all the exporter
+/// adds. Any other whitespace belongs to the title.
+fn parse_heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|c| *c == '*').count();
+    if level == 0 {
+        return None;
+    }
+    let rest = &line[level..];
+    if rest.is_empty() {
+        return Some((level, rest));
+    }
+    Some((level, rest.strip_prefix(' ')?))
+}
+
+fn unescape_line(line: &str) -> &str {
+    match line.strip_prefix(',') {
+        Some(rest) if rest.starts_with(['*', ',']) => rest,
+        _ => line,
+    }
+}
+
+struct Heading {
+    level: usize,
+    id: Option<NoteId>,
+    title: String,
+    body: Vec<String>,
+}
+
+impl Heading {
+    fn text(&self) -> String {
+        let mut text = self.title.clone();
+        for line in &self.body {
+            text.push('\n');
+            text.push_str(line);
+        }
+        text
+    }
+}
+
+/// Split an Org document into headings.
+///
+/// Anything before the first heading is ignored.
+fn parse_headings(org: &str) -> Vec<Heading> {
+    let mut headings: Vec<Heading> = vec![];
+    let mut at_heading = false;
+    let mut in_drawer = false;
+
+    for line in org.lines() {
+        if let Some((level, title)) = parse_heading(line) {
+            headings.push(Heading {
+                level,
+                id: None,
+                title: title.to_string(),
+                body: vec![],
+            });
+            at_heading = true;
+            in_drawer = false;
+            continue;
+        }
+
+        let Some(heading) = headings.last_mut() else {
+            continue;
+        };
+
+        // Property drawers must directly follow their heading.
+        let trimmed = line.trim();
+        let drawer_allowed = at_heading;
+        at_heading = false;
+
+        if drawer_allowed && trimmed.eq_ignore_ascii_case(":PROPERTIES:") {
+            in_drawer = true;
+        } else if in_drawer && trimmed.eq_ignore_ascii_case(":END:") {
+            in_drawer = false;
+        } else if in_drawer {
+            // Ids that aren't note ids, like the UUIDs created by `org-id`,
+            // are ignored and the note receives a fresh id.
+            let id = trimmed
+                .strip_prefix(&format!(":{ID_PROPERTY}:"))
+                .and_then(|value| value.trim().parse().ok());
+            if let Some(id) = id {
+                heading.id = Some(id);
+            }
+        } else {
+            heading.body.push(unescape_line(line).to_string());
+        }
+    }
+
+    headings
+}
+
+/// Import an Org document.
+///
+/// Every heading becomes a note with its nested headings as ordered children.
+/// The heading title is the first line of the note's text, the text below the
+/// heading the remaining lines. Whitespace is kept, so notes that weren't
+/// edited stay the same. Headings with a note id in their `:ID:` property
+/// keep that id, so documents produced by [`crate::export::org`] can be
+/// edited and imported again to update the existing notes. If an id
+/// appears multiple times, the first occurrence defines the note and all
+/// later ones only reference it. All other headings receive fresh ids that are
+/// not in `taken`, which should contain the ids of the repo the notes are
+/// imported into.
+///
+/// No notes are modified or saved, that is up to the caller.
+pub fn import(org: &str, taken: &HashSet<NoteId>) -> Outline {
+    let mut builder = OutlineBuilder::new(taken);
+    let mut levels: Vec<usize> = vec![];
+
+    for heading in parse_headings(org) {
+        while levels.last().is_some_and(|level| *level >= heading.level) {
+            levels.pop();
+            builder.close();
+        }
+
+        builder.open(heading.id, heading.text());
+        levels.push(heading.level);
+    }
+
+    for _ in levels {
+        builder.close();
+    }
+
+    builder.finish()
+}
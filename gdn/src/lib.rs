@@ -1,7 +1,14 @@
+pub mod crypto;
 pub mod data;
+pub mod duplicates;
+pub mod export;
 pub mod ids;
+pub mod import;
+pub mod links;
 pub mod repo;
+pub mod review;
 pub mod store;
+pub mod tags;
 
 pub const PROPER_NAME: &str = "GedächtNAS";
 pub const TECHNICAL_NAME: &str = "gedaechtnas";
@@ -0,0 +1,66 @@
+use std::ops::Range;
+
+use crate::ids::NoteId;
+
+/// The byte ranges of the targets of all links in a text, along with the
+/// targets themselves.
+fn find(text: &str) -> Vec<(Range<usize>, NoteId)> {
+    let mut result = vec![];
+    let mut rest = 0;
+
+    while let Some(start) = text[rest..].find("[[").map(|it| rest + it) {
+        let inner_start = start + 2;
+        let Some(inner_end) = text[inner_start..].find("]]").map(|it| inner_start + it) else {
+            break;
+        };
+        let inner = &text[inner_start..inner_end];
+
+        // Links don't span lines, so a link may start after an unclosed one.
+        if inner.contains(['\n', '[']) {
+            rest = inner_start;
+            continue;
+        }
+
+        let target = inner.split_once('|').map_or(inner, |(id, _)| id);
+        let target_start = inner_start + (target.len() - target.trim_start().len());
+        let target = target.trim();
+        if let Ok(id) = target.parse() {
+            result.push((target_start..target_start + target.len(), id));
+        }
+        rest = inner_end + 2;
+    }
+
+    result
+}
+
+/// The targets of all links in a text, in order of their first occurrence.
+///
+/// Links have the form `[[<id>]]`, optionally with a label as in
+/// `[[<id>|<label>]]`. Unlike children, they don't affect the structure of the
+/// notes.
+pub fn parse(text: &str) -> Vec<NoteId> {
+    let mut result = vec![];
+    for (_, id) in find(text) {
+        if !result.contains(&id) {
+            result.push(id);
+        }
+    }
+    result
+}
+
+/// Replace the target of every link to a note in a text with a different note,
+/// keeping the labels.
+pub fn retarget(text: &str, from: NoteId, to: NoteId) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for (range, id) in find(text) {
+        if id != from {
+            continue;
+        }
+        result.push_str(&text[last..range.start]);
+        result.push_str(&to.to_string());
+        last = range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}
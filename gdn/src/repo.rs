@@ -1,13 +1,22 @@
 mod v0;
 mod v1;
+mod v2;
+mod v3;
+mod v4;
+mod v5;
 
-use std::path::Path;
+use std::{num::NonZero, path::Path, thread};
 
 use anyhow::{anyhow, bail};
-use git2::{Commit, ErrorCode, FileMode, Oid, Reference, Repository, TreeBuilder};
+use git2::{Commit, ErrorCode, FileMode, Oid, Reference, Repository, Tree, TreeBuilder};
 use jiff::Zoned;
 
-pub use self::v1::{Note, Repo, VERSION};
+use crate::crypto::{Scheme, Secret};
+
+pub use self::v5::{
+    Attachment, Grade, LazyRepo, Metadata, Note, Repo, Review, ReviewRecord, TrashedNote,
+    TrashedParent, VERSION, attachment_path,
+};
 
 const VERSION_FILE: &str = "VERSION";
 
@@ -56,7 +65,36 @@ pub fn load_version(path: &Path) -> anyhow::Result<u32> {
     Ok(version)
 }
 
-pub fn load(path: &Path) -> anyhow::Result<Repo> {
+/// Read the encryption scheme of a repo, if it is encrypted.
+pub fn load_scheme(path: &Path) -> anyhow::Result<Option<Scheme>> {
+    let repository = Repository::open_bare(path)?;
+    let Some(head) = read_head(&repository)? else {
+        return Ok(None);
+    };
+    let commit = head.peel_to_commit()?;
+    // Older versions can't be encrypted, and newer ones can't be loaded.
+    if read_version(&repository, &commit)? != VERSION {
+        return Ok(None);
+    }
+    v5::load_scheme(&repository, &commit.tree()?)
+}
+
+/// Load a repo, migrating it to the current version.
+///
+/// Encrypted repos can only be loaded with the correct secret.
+pub fn load(path: &Path, secret: Option<&Secret>) -> anyhow::Result<Repo> {
+    let threads = thread::available_parallelism().map_or(1, NonZero::get);
+    load_with_threads(path, secret, threads)
+}
+
+/// Like [`load`], but with a specific number of threads reading the notes.
+///
+/// Repos of older versions are always read by a single thread.
+pub fn load_with_threads(
+    path: &Path,
+    secret: Option<&Secret>,
+    threads: usize,
+) -> anyhow::Result<Repo> {
     let repository = Repository::open_bare(path)?;
     let Some(head) = read_head(&repository)? else {
         return Ok(v0::Repo::load().migrate());
@@ -66,24 +104,99 @@ pub fn load(path: &Path) -> anyhow::Result<Repo> {
     let tree = commit.tree()?;
 
     #[expect(unused_qualifications)]
-    let repo = match version {
+    let mut repo = match version {
         v1::VERSION => v1::Repo::load_from_tree(&repository, &tree)?.migrate(),
+        v2::VERSION => v2::Repo::load_from_tree(&repository, &tree)?.migrate(),
+        v3::VERSION => v3::Repo::load_from_tree(&repository, &tree)?.migrate(),
+        v4::VERSION => v4::Repo::load_from_tree(&repository, &tree)?.migrate(),
+        v5::VERSION => v5::Repo::load_from_tree(&repository, &tree, secret, threads)?.migrate(),
         n => bail!("invalid repo version {n}"),
     };
+    repo.base = Some(commit.id());
 
     Ok(repo)
 }
 
-pub fn save(path: &Path, repo: Repo) -> anyhow::Result<Oid> {
+/// Load a repo without reading its notes, see [`LazyRepo`].
+///
+/// Only repos of the current version can be loaded this way.
+pub fn load_lazy(path: &Path, secret: Option<&Secret>) -> anyhow::Result<LazyRepo> {
+    let repository = Repository::open_bare(path)?;
+    let tree = match read_head(&repository)? {
+        None => None,
+        Some(head) => {
+            let commit = head.peel_to_commit()?;
+            let version = read_version(&repository, &commit)?;
+            if version != VERSION {
+                bail!("repo version {version} must be migrated before it can be loaded lazily");
+            }
+            Some(commit.tree_id())
+        }
+    };
+    LazyRepo::load(repository, tree, secret)
+}
+
+/// Write the content of an attachment to the repository, returning its hash.
+///
+/// The attachment only becomes part of the repo once a note referencing it is
+/// saved.
+pub fn write_attachment(path: &Path, content: &[u8]) -> anyhow::Result<String> {
     let repository = Repository::open_bare(path)?;
+    let oid = repository.blob(content)?;
+    Ok(oid.to_string())
+}
 
-    let mut tree = repository.treebuilder(None)?;
-    write_version(&repository, &mut tree)?;
-    repo.save_to_tree(&repository, &mut tree)?;
-    let tree = repository.find_tree(tree.write()?)?;
+/// Read the content of an attachment from the repository.
+pub fn read_attachment(path: &Path, hash: &str) -> anyhow::Result<Vec<u8>> {
+    let repository = Repository::open_bare(path)?;
+    let oid = Oid::from_str(hash)?;
+    let blob = repository
+        .find_blob(oid)
+        .map_err(|_| anyhow!("no attachment with hash {hash}"))?;
+    Ok(blob.content().to_vec())
+}
 
-    let signature = repository.signature()?;
-    let message = Zoned::now().to_string();
+/// The tree a repo can be saved on top of, see [`Repo::can_update`].
+///
+/// This is only the case if the repo was loaded from the current head, which
+/// has the current version.
+fn find_base_tree<'a>(
+    repository: &'a Repository,
+    repo: &Repo,
+    head: Option<&Commit<'a>>,
+) -> anyhow::Result<Option<Tree<'a>>> {
+    let Some(head) = head else {
+        return Ok(None);
+    };
+    if repo.base != Some(head.id()) || read_version(repository, head)? != VERSION {
+        return Ok(None);
+    }
+    let tree = head.tree()?;
+    if !repo.can_update(repository, &tree)? {
+        return Ok(None);
+    }
+    Ok(Some(tree))
+}
+
+/// A commit written by [`prepare_save`] that the head doesn't point to yet.
+pub struct PreparedSave {
+    commit: Oid,
+    parent: Option<Oid>,
+}
+
+/// The name of the reference the head points to, usually a branch.
+fn head_name(repository: &Repository) -> anyhow::Result<String> {
+    let head = repository.find_reference("HEAD")?;
+    Ok(head.symbolic_target().unwrap_or("HEAD").to_string())
+}
+
+/// Write a repo as a new commit on top of the current head without moving the
+/// head, see [`finish_save`].
+///
+/// If the repo was loaded from the current head and knows which notes
+/// changed, only the parts of the tree containing these notes are rewritten.
+pub fn prepare_save(path: &Path, repo: Repo) -> anyhow::Result<PreparedSave> {
+    let repository = Repository::open_bare(path)?;
 
     // TODO Check that the repo is actually based on this commit.
     // TODO Check if there actually is a difference to the parent commit
@@ -91,19 +204,70 @@ pub fn save(path: &Path, repo: Repo) -> anyhow::Result<Oid> {
         None => None,
         Some(parent) => Some(parent.peel_to_commit()?),
     };
+
+    let base = find_base_tree(&repository, &repo, parent.as_ref())?;
+    let mut tree = repository.treebuilder(base.as_ref())?;
+    write_version(&repository, &mut tree)?;
+    repo.save_to_tree(&repository, base.as_ref(), &mut tree)?;
+    let tree = repository.find_tree(tree.write()?)?;
+
+    let signature = repository.signature()?;
+    let message = Zoned::now().to_string();
+
     let parents = match &parent {
         None => vec![],
         Some(parent) => vec![parent],
     };
 
-    let oid = repository.commit(
-        Some("HEAD"),
-        &signature,
-        &signature,
-        &message,
-        &tree,
-        &parents,
-    )?;
+    let commit = repository.commit(None, &signature, &signature, &message, &tree, &parents)?;
+
+    Ok(PreparedSave {
+        commit,
+        parent: parent.map(|it| it.id()),
+    })
+}
+
+/// Move the head to a commit written by [`prepare_save`].
+///
+/// Fails if the head has moved since the commit was prepared.
+pub fn finish_save(path: &Path, prepared: &PreparedSave) -> anyhow::Result<Oid> {
+    let repository = Repository::open_bare(path)?;
+    let name = head_name(&repository)?;
+    let message = "commit";
+    match prepared.parent {
+        None => repository.reference(&name, prepared.commit, false, message)?,
+        Some(parent) => {
+            repository.reference_matching(&name, prepared.commit, true, parent, message)?
+        }
+    };
+    Ok(prepared.commit)
+}
 
-    Ok(oid)
+/// Move the head back to where it was before [`finish_save`].
+///
+/// Fails if the head has moved since.
+pub fn undo_save(path: &Path, prepared: &PreparedSave) -> anyhow::Result<()> {
+    let repository = Repository::open_bare(path)?;
+    let name = head_name(&repository)?;
+    let message = "undo commit";
+    match prepared.parent {
+        None => {
+            let mut reference = repository.find_reference(&name)?;
+            if reference.target() != Some(prepared.commit) {
+                bail!("head of {} has moved", path.display());
+            }
+            reference.delete()?;
+        }
+        Some(parent) => {
+            repository.reference_matching(&name, parent, true, prepared.commit, message)?;
+        }
+    }
+    Ok(())
+}
+
+/// Save a repo as a new commit on top of the current head.
+///
+/// See [`prepare_save`] and [`finish_save`].
+pub fn save(path: &Path, repo: Repo) -> anyhow::Result<Oid> {
+    finish_save(path, &prepare_save(path, repo)?)
 }
@@ -1,4 +1,4 @@
-use super::v1;
+use super::v5;
 
 pub const VERSION: u32 = 0;
 
@@ -10,6 +10,6 @@ impl Repo {
     }
 
     pub fn migrate(self) -> super::Repo {
-        v1::Repo { notes: vec![] }.migrate()
+        v5::Repo::default().migrate()
     }
 }
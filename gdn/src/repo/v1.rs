@@ -1,9 +1,11 @@
 use anyhow::anyhow;
-use git2::{FileMode, Repository, Tree, TreeBuilder, TreeEntry, TreeWalkMode, TreeWalkResult};
+use git2::{Repository, Tree, TreeEntry, TreeWalkMode, TreeWalkResult};
 use serde::{Deserialize, Serialize};
 
 use crate::ids::NoteId;
 
+use super::v2;
+
 pub const VERSION: u32 = 1;
 
 #[derive(Serialize, Deserialize)]
@@ -13,35 +15,10 @@ pub struct Note {
     pub children: Vec<NoteId>,
 }
 
-#[derive(Default)]
 pub struct Repo {
     pub notes: Vec<Note>,
 }
 
-fn add_note_to_tree(
-    repository: &Repository,
-    target: &mut TreeBuilder<'_>,
-    note: &Note,
-) -> anyhow::Result<()> {
-    let filename = format!("{}.json", note.id);
-    let oid = repository.blob(&serde_json::to_vec(note)?)?;
-    target.insert(filename, oid, FileMode::Blob.into())?;
-    Ok(())
-}
-
-fn add_tree_to_tree(
-    target: &mut TreeBuilder<'_>,
-    tree: &TreeBuilder<'_>,
-    filename: String,
-) -> anyhow::Result<()> {
-    if tree.is_empty() {
-        return Ok(());
-    }
-    let oid = tree.write()?;
-    target.insert(filename, oid, FileMode::Tree.into())?;
-    Ok(())
-}
-
 fn load_note(
     repository: &Repository,
     entry: &TreeEntry<'_>,
@@ -62,12 +39,14 @@ impl Repo {
         let mut notes = vec![];
         let mut error: Option<anyhow::Error> = None;
 
-        tree.walk(TreeWalkMode::PreOrder, |name, entry| {
-            if name.ends_with(".json") {
-                if let Err(err) = load_note(repository, entry, &mut notes) {
-                    error = Some(err);
-                    return TreeWalkResult::Abort;
-                }
+        // The first argument is the path of the directory containing the entry,
+        // not the name of the entry itself.
+        tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+            if entry.name().is_some_and(|name| name.ends_with(".json"))
+                && let Err(err) = load_note(repository, entry, &mut notes)
+            {
+                error = Some(err);
+                return TreeWalkResult::Abort;
             }
             TreeWalkResult::Ok
         })?;
@@ -79,52 +58,21 @@ impl Repo {
         Ok(Self { notes })
     }
 
-    pub fn save_to_tree(
-        mut self,
-        repository: &Repository,
-        tree: &mut TreeBuilder<'_>,
-    ) -> anyhow::Result<()> {
-        self.notes.sort_unstable_by_key(|it| it.id);
-
-        let mut year = 0;
-        let mut year_tree = repository.treebuilder(None)?;
-        let mut month = 0;
-        let mut month_tree = repository.treebuilder(None)?;
-        let mut day = 0;
-        let mut day_tree = repository.treebuilder(None)?;
-
-        for note in self.notes {
-            let time = note.id.time_utc();
-
-            if day != time.day() || month != time.month() || year != time.year() {
-                add_tree_to_tree(&mut month_tree, &day_tree, format!("{day:02}"))?;
-                day_tree.clear()?;
-                day = time.day();
-            }
-
-            if month != time.month() || year != time.year() {
-                add_tree_to_tree(&mut year_tree, &month_tree, format!("{month:02}"))?;
-                month_tree.clear()?;
-                month = time.month();
-            }
-
-            if year != time.year() {
-                add_tree_to_tree(tree, &year_tree, format!("{year:04}"))?;
-                year_tree.clear()?;
-                year = time.year();
-            }
-
-            add_note_to_tree(repository, &mut day_tree, &note)?;
+    pub fn migrate(self) -> super::Repo {
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|note| v2::Note {
+                id: note.id,
+                text: note.text,
+                children: note.children,
+            })
+            .collect();
+
+        v2::Repo {
+            notes,
+            ..v2::Repo::default()
         }
-
-        add_tree_to_tree(&mut month_tree, &day_tree, format!("{day:02}"))?;
-        add_tree_to_tree(&mut year_tree, &month_tree, format!("{month:02}"))?;
-        add_tree_to_tree(tree, &year_tree, format!("{year:04}"))?;
-
-        Ok(())
-    }
-
-    pub fn migrate(self) -> Self {
-        self
+        .migrate()
     }
 }
@@ -0,0 +1,104 @@
+use std::collections::HashMap;
+
+use anyhow::anyhow;
+use git2::{Repository, Tree, TreeEntry, TreeWalkMode, TreeWalkResult};
+use serde::{Deserialize, Serialize};
+
+use crate::ids::NoteId;
+
+// The review format didn't change in later versions.
+use super::{v3, v4::Review};
+
+pub const VERSION: u32 = 2;
+
+const NOTE_SUFFIX: &str = ".json";
+const REVIEW_SUFFIX: &str = ".review.json";
+
+#[derive(Serialize, Deserialize)]
+pub struct Note {
+    pub id: NoteId,
+    pub text: String,
+    pub children: Vec<NoteId>,
+}
+
+/// A [`Review`] as it is stored in the repo, next to the note it belongs to.
+#[derive(Serialize, Deserialize)]
+struct ReviewFile {
+    id: NoteId,
+    #[serde(flatten)]
+    review: Review,
+}
+
+#[derive(Default)]
+pub struct Repo {
+    pub notes: Vec<Note>,
+    pub reviews: HashMap<NoteId, Review>,
+}
+
+fn read_blob(repository: &Repository, entry: &TreeEntry<'_>) -> anyhow::Result<Vec<u8>> {
+    let object = entry.to_object(repository)?;
+    let content = object
+        .as_blob()
+        .ok_or(anyhow!("json file is not a blob!?"))?
+        .content()
+        .to_vec();
+    Ok(content)
+}
+
+impl Repo {
+    fn load_entry(&mut self, repository: &Repository, entry: &TreeEntry<'_>) -> anyhow::Result<()> {
+        let Some(name) = entry.name() else {
+            return Ok(());
+        };
+
+        if name.ends_with(REVIEW_SUFFIX) {
+            let file = serde_json::from_slice::<ReviewFile>(&read_blob(repository, entry)?)?;
+            self.reviews.insert(file.id, file.review);
+        } else if name.ends_with(NOTE_SUFFIX) {
+            let note = serde_json::from_slice::<Note>(&read_blob(repository, entry)?)?;
+            self.notes.push(note);
+        }
+
+        Ok(())
+    }
+
+    pub fn load_from_tree(repository: &Repository, tree: &Tree<'_>) -> anyhow::Result<Self> {
+        let mut result = Self::default();
+        let mut error: Option<anyhow::Error> = None;
+
+        // The first argument is the path of the directory containing the entry,
+        // not the name of the entry itself.
+        tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+            if let Err(err) = result.load_entry(repository, entry) {
+                error = Some(err);
+                return TreeWalkResult::Abort;
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        Ok(result)
+    }
+
+    pub fn migrate(self) -> super::Repo {
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|note| v3::Note {
+                id: note.id,
+                text: note.text,
+                children: note.children,
+                meta: v3::Metadata::default(),
+            })
+            .collect();
+
+        v3::Repo {
+            notes,
+            reviews: self.reviews,
+        }
+        .migrate()
+    }
+}
@@ -0,0 +1,126 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::anyhow;
+use git2::{Repository, Tree, TreeEntry, TreeWalkMode, TreeWalkResult};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::ids::NoteId;
+
+// The review format didn't change in later versions.
+use super::v4::{self, Review};
+
+pub const VERSION: u32 = 3;
+
+const NOTE_SUFFIX: &str = ".json";
+const REVIEW_SUFFIX: &str = ".review.json";
+
+/// Optional information about a note.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    /// When the note was last modified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<Timestamp>,
+    /// The name of the device the note was created on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Arbitrary key-value pairs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Note {
+    pub id: NoteId,
+    pub text: String,
+    pub children: Vec<NoteId>,
+    #[serde(flatten)]
+    pub meta: Metadata,
+}
+
+/// A [`Review`] as it is stored in the repo, next to the note it belongs to.
+#[derive(Serialize, Deserialize)]
+struct ReviewFile {
+    id: NoteId,
+    #[serde(flatten)]
+    review: Review,
+}
+
+#[derive(Default)]
+pub struct Repo {
+    pub notes: Vec<Note>,
+    pub reviews: HashMap<NoteId, Review>,
+}
+
+fn read_blob(repository: &Repository, entry: &TreeEntry<'_>) -> anyhow::Result<Vec<u8>> {
+    let object = entry.to_object(repository)?;
+    let content = object
+        .as_blob()
+        .ok_or(anyhow!("json file is not a blob!?"))?
+        .content()
+        .to_vec();
+    Ok(content)
+}
+
+impl Repo {
+    fn load_entry(&mut self, repository: &Repository, entry: &TreeEntry<'_>) -> anyhow::Result<()> {
+        let Some(name) = entry.name() else {
+            return Ok(());
+        };
+
+        if name.ends_with(REVIEW_SUFFIX) {
+            let file = serde_json::from_slice::<ReviewFile>(&read_blob(repository, entry)?)?;
+            self.reviews.insert(file.id, file.review);
+        } else if name.ends_with(NOTE_SUFFIX) {
+            let note = serde_json::from_slice::<Note>(&read_blob(repository, entry)?)?;
+            self.notes.push(note);
+        }
+
+        Ok(())
+    }
+
+    pub fn load_from_tree(repository: &Repository, tree: &Tree<'_>) -> anyhow::Result<Self> {
+        let mut result = Self::default();
+        let mut error: Option<anyhow::Error> = None;
+
+        // The first argument is the path of the directory containing the entry,
+        // not the name of the entry itself.
+        tree.walk(TreeWalkMode::PreOrder, |_, entry| {
+            if let Err(err) = result.load_entry(repository, entry) {
+                error = Some(err);
+                return TreeWalkResult::Abort;
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        if let Some(err) = error {
+            return Err(err);
+        }
+
+        Ok(result)
+    }
+
+    pub fn migrate(self) -> super::Repo {
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|note| v4::Note {
+                id: note.id,
+                text: note.text,
+                children: note.children,
+                meta: v4::Metadata {
+                    modified: note.meta.modified,
+                    device: note.meta.device,
+                    properties: note.meta.properties,
+                    attachments: vec![],
+                },
+            })
+            .collect();
+
+        v4::Repo {
+            notes,
+            reviews: self.reviews,
+        }
+        .migrate()
+    }
+}
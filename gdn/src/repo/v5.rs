@@ -0,0 +1,840 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    panic, thread,
+};
+
+use anyhow::{Context, anyhow, bail};
+use git2::{FileMode, Oid, Repository, Tree, TreeBuilder, TreeEntry, TreeWalkMode, TreeWalkResult};
+use jiff::Timestamp;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    crypto::{Encryption, Scheme, Secret},
+    ids::NoteId,
+};
+
+pub const VERSION: u32 = 5;
+
+const NOTE_SUFFIX: &str = ".json";
+const REVIEW_SUFFIX: &str = ".review.json";
+
+/// The file describing how the repo is encrypted, if it is.
+pub const ENCRYPTION_FILE: &str = "ENCRYPTION";
+
+/// The file in each day's directory listing the children of the notes in that
+/// directory, see [`LazyRepo`].
+///
+/// Its name doesn't end in [`NOTE_SUFFIX`], so it is ignored when loading all
+/// notes at once.
+pub const CHILDREN_FILE: &str = "CHILDREN";
+
+/// The file containing the trash, see [`TrashedNote`].
+///
+/// Like [`CHILDREN_FILE`], it is ignored when loading all notes at once.
+pub const TRASH_FILE: &str = "TRASH";
+
+/// The directory containing all attachments, see [`attachment_path`].
+pub const ATTACHMENTS_DIR: &str = "attachments";
+
+/// A file attached to a note.
+///
+/// The file's content is stored as a git blob, so it is identified by its
+/// blob id. Identical files attached to different notes are only stored once.
+#[derive(Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attachment {
+    /// The file name, unique among the attachments of a note.
+    pub name: String,
+    /// The hex representation of the git blob id.
+    pub hash: String,
+    /// The file size in bytes.
+    pub size: u64,
+}
+
+/// Optional information about a note.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct Metadata {
+    /// When the note was last modified.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub modified: Option<Timestamp>,
+    /// The name of the device the note was created on.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device: Option<String>,
+    /// Arbitrary key-value pairs.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub properties: BTreeMap<String, String>,
+    /// Files attached to the note.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attachments: Vec<Attachment>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Note {
+    pub id: NoteId,
+    pub text: String,
+    pub children: Vec<NoteId>,
+    #[serde(flatten)]
+    pub meta: Metadata,
+}
+
+/// How well a card was remembered during a review.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Grade {
+    Again,
+    Hard,
+    Good,
+    Easy,
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub struct ReviewRecord {
+    pub time: Timestamp,
+    pub grade: Grade,
+}
+
+/// The review state of a single note.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct Review {
+    pub due: Timestamp,
+    /// The current interval in days.
+    pub interval: u32,
+    /// The ease factor in permille.
+    pub ease: u32,
+    /// The number of successful reviews in a row.
+    pub repetitions: u32,
+    /// The number of times the note was forgotten after being learned.
+    pub lapses: u32,
+    pub history: Vec<ReviewRecord>,
+}
+
+/// A place where a deleted note used to be a child.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct TrashedParent {
+    pub id: NoteId,
+    /// The index of the note in the parent's children.
+    pub index: usize,
+}
+
+/// A deleted note, kept in the [`TRASH_FILE`] until it is restored or purged.
+#[derive(Serialize, Deserialize)]
+pub struct TrashedNote {
+    pub deleted: Timestamp,
+    /// Where the note used to be a child, in the order of the indices.
+    pub parents: Vec<TrashedParent>,
+    pub note: Note,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub review: Option<Review>,
+}
+
+/// A [`Review`] as it is stored in the repo, next to the note it belongs to.
+#[derive(Serialize, Deserialize)]
+struct ReviewFile {
+    id: NoteId,
+    #[serde(flatten)]
+    review: Review,
+}
+
+/// Notes and reviews read by one thread, see [`Repo::load_from_tree`].
+#[derive(Default)]
+struct Loaded {
+    notes: Vec<Note>,
+    reviews: Vec<(NoteId, Review)>,
+}
+
+/// The notes of a repo.
+///
+/// If the repo is encrypted, notes and reviews are stored encrypted, while the
+/// tree structure and [`ENCRYPTION_FILE`] are not. Attachments are never
+/// encrypted, so encrypted repos shouldn't have any.
+#[derive(Default)]
+pub struct Repo {
+    pub notes: Vec<Note>,
+    pub reviews: HashMap<NoteId, Review>,
+    /// Deleted notes, see [`TRASH_FILE`].
+    pub trash: Vec<TrashedNote>,
+    pub encryption: Option<Encryption>,
+    /// The commit the repo was loaded from, if any.
+    pub base: Option<Oid>,
+    /// The notes that were created, modified or deleted since loading the repo
+    /// from [`Self::base`], including changes to their reviews.
+    ///
+    /// If this is [`None`], every note is considered modified.
+    pub dirty: Option<HashSet<NoteId>>,
+}
+
+fn add_blob_to_tree(
+    repository: &Repository,
+    target: &mut TreeBuilder<'_>,
+    filename: String,
+    content: &[u8],
+) -> anyhow::Result<()> {
+    let oid = repository.blob(content)?;
+    target.insert(filename, oid, FileMode::Blob.into())?;
+    Ok(())
+}
+
+fn add_tree_to_tree(
+    target: &mut TreeBuilder<'_>,
+    tree: &TreeBuilder<'_>,
+    filename: String,
+) -> anyhow::Result<()> {
+    if tree.is_empty() {
+        return Ok(());
+    }
+    let oid = tree.write()?;
+    target.insert(filename, oid, FileMode::Tree.into())?;
+    Ok(())
+}
+
+/// Like [`add_tree_to_tree`], but removes an existing entry if the tree is
+/// empty.
+fn replace_tree_in_tree(
+    target: &mut TreeBuilder<'_>,
+    tree: &TreeBuilder<'_>,
+    filename: String,
+) -> anyhow::Result<()> {
+    if tree.is_empty() {
+        if target.get(&filename)?.is_some() {
+            target.remove(&filename)?;
+        }
+        return Ok(());
+    }
+    add_tree_to_tree(target, tree, filename)
+}
+
+fn find_subtree<'a>(
+    repository: &'a Repository,
+    tree: Option<&Tree<'a>>,
+    name: &str,
+) -> anyhow::Result<Option<Tree<'a>>> {
+    let Some(entry) = tree.and_then(|it| it.get_name(name)) else {
+        return Ok(None);
+    };
+    Ok(Some(entry.to_object(repository)?.peel_to_tree()?))
+}
+
+fn split_hash(hash: &str) -> anyhow::Result<(&str, &str)> {
+    if hash.len() != 40 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        bail!("invalid attachment hash {hash:?}");
+    }
+    Ok(hash.split_at(2))
+}
+
+/// The path of an attachment's blob relative to the root of the tree.
+///
+/// Attachments are stored in [`ATTACHMENTS_DIR`] in subdirectories named
+/// after the first two characters of their hash, like git objects.
+pub fn attachment_path(hash: &str) -> anyhow::Result<String> {
+    let (prefix, rest) = split_hash(hash)?;
+    Ok(format!("{ATTACHMENTS_DIR}/{prefix}/{rest}"))
+}
+
+fn read_blob(repository: &Repository, entry: &TreeEntry<'_>) -> anyhow::Result<Vec<u8>> {
+    let object = entry.to_object(repository)?;
+    let content = object
+        .as_blob()
+        .ok_or(anyhow!("json file is not a blob!?"))?
+        .content()
+        .to_vec();
+    Ok(content)
+}
+
+/// Read the encryption scheme of a tree, if it has one.
+pub fn load_scheme(repository: &Repository, tree: &Tree<'_>) -> anyhow::Result<Option<Scheme>> {
+    let Some(entry) = tree.get_name(ENCRYPTION_FILE) else {
+        return Ok(None);
+    };
+    let scheme = serde_json::from_slice(&read_blob(repository, &entry)?)
+        .with_context(|| format!("failed to read {ENCRYPTION_FILE}"))?;
+    Ok(Some(scheme))
+}
+
+/// Decrypt the content of a file if necessary.
+///
+/// The name is used as associated data, see [`crate::crypto::Key`]. For note
+/// and review files, it is the file name, for [`CHILDREN_FILE`] the full path
+/// and for [`TRASH_FILE`] its name.
+fn decode(
+    encryption: Option<&Encryption>,
+    name: &str,
+    content: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    match encryption {
+        None => Ok(content),
+        Some(encryption) => encryption
+            .key
+            .decrypt(&content, name.as_bytes())
+            .with_context(|| format!("failed to decrypt {name}")),
+    }
+}
+
+/// Encrypt the content of a file if necessary, see [`decode`].
+fn encode(
+    encryption: Option<&Encryption>,
+    name: &str,
+    content: Vec<u8>,
+) -> anyhow::Result<Vec<u8>> {
+    match encryption {
+        None => Ok(content),
+        Some(encryption) => encryption.key.encrypt(&content, name.as_bytes()),
+    }
+}
+
+/// Unlock the encryption of a tree, if it is encrypted.
+fn unlock(
+    repository: &Repository,
+    tree: &Tree<'_>,
+    secret: Option<&Secret>,
+) -> anyhow::Result<Option<Encryption>> {
+    let Some(scheme) = load_scheme(repository, tree)? else {
+        return Ok(None);
+    };
+    let Some(secret) = secret else {
+        bail!("repo is encrypted, a passphrase or keyfile is required");
+    };
+    let key = scheme.unlock(secret)?;
+    Ok(Some(Encryption { scheme, key }))
+}
+
+/// The path of a day's directory.
+fn day_path(year: i16, month: i8, day: i8) -> String {
+    format!("{year:04}/{month:02}/{day:02}")
+}
+
+impl Repo {
+    /// Encrypt the content of a note or review file if necessary.
+    fn encode_file(&self, filename: &str, content: Vec<u8>) -> anyhow::Result<Vec<u8>> {
+        encode(self.encryption.as_ref(), filename, content)
+    }
+
+    /// Store the children of the notes of a day in its [`CHILDREN_FILE`], or
+    /// remove the file if there are no notes.
+    fn add_children_to_tree(
+        &self,
+        repository: &Repository,
+        day_tree: &mut TreeBuilder<'_>,
+        path: &str,
+        children: &BTreeMap<NoteId, &[NoteId]>,
+    ) -> anyhow::Result<()> {
+        if children.is_empty() {
+            if day_tree.get(CHILDREN_FILE)?.is_some() {
+                day_tree.remove(CHILDREN_FILE)?;
+            }
+            return Ok(());
+        }
+        let name = format!("{path}/{CHILDREN_FILE}");
+        let content = encode(
+            self.encryption.as_ref(),
+            &name,
+            serde_json::to_vec(children)?,
+        )?;
+        add_blob_to_tree(repository, day_tree, CHILDREN_FILE.to_string(), &content)
+    }
+
+    /// Read and parse note and review files.
+    fn load_files(
+        &self,
+        repository: &Repository,
+        files: &[(String, Oid)],
+    ) -> anyhow::Result<Loaded> {
+        let mut result = Loaded::default();
+        for (name, oid) in files {
+            let content = repository.find_blob(*oid)?.content().to_vec();
+            let content = decode(self.encryption.as_ref(), name, content)?;
+            if name.ends_with(REVIEW_SUFFIX) {
+                let file = serde_json::from_slice::<ReviewFile>(&content)?;
+                result.reviews.push((file.id, file.review));
+            } else {
+                result.notes.push(serde_json::from_slice::<Note>(&content)?);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Load all notes from a tree, reading and parsing them in parallel.
+    ///
+    /// If the tree is encrypted, a secret is required to unlock it. Each
+    /// thread opens its own handle to the repository, since handles can't be
+    /// shared between threads. The result doesn't depend on the number of
+    /// threads.
+    pub fn load_from_tree(
+        repository: &Repository,
+        tree: &Tree<'_>,
+        secret: Option<&Secret>,
+        threads: usize,
+    ) -> anyhow::Result<Self> {
+        let mut result = Self {
+            encryption: unlock(repository, tree, secret)?,
+            ..Self::default()
+        };
+
+        // The first argument is the path of the directory containing the entry,
+        // not the name of the entry itself.
+        let mut files = vec![];
+        tree.walk(TreeWalkMode::PreOrder, |path, entry| {
+            // Attachments are only loaded on demand.
+            if path.is_empty() && entry.name() == Some(ATTACHMENTS_DIR) {
+                return TreeWalkResult::Skip;
+            }
+            // This includes review files.
+            if let Some(name) = entry.name()
+                && name.ends_with(NOTE_SUFFIX)
+            {
+                files.push((name.to_string(), entry.id()));
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        // Every thread loads a contiguous chunk of files, and the chunks are
+        // combined in order, so notes end up in the same order as in the tree.
+        let chunk_size = files.len().div_ceil(threads.max(1)).max(1);
+        let loaded = if files.len() <= chunk_size {
+            vec![result.load_files(repository, &files)]
+        } else {
+            let path = repository.path();
+            let result = &result;
+            thread::scope(|scope| {
+                let handles = files
+                    .chunks(chunk_size)
+                    .map(|chunk| {
+                        scope.spawn(move || {
+                            let repository = Repository::open_bare(path)?;
+                            result.load_files(&repository, chunk)
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                handles
+                    .into_iter()
+                    .map(|it| it.join().unwrap_or_else(|err| panic::resume_unwind(err)))
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        for loaded in loaded {
+            let loaded = loaded?;
+            result.notes.extend(loaded.notes);
+            result.reviews.extend(loaded.reviews);
+        }
+
+        result.load_trash(repository, tree)?;
+
+        Ok(result)
+    }
+
+    fn load_trash(&mut self, repository: &Repository, tree: &Tree<'_>) -> anyhow::Result<()> {
+        let Some(entry) = tree.get_name(TRASH_FILE) else {
+            return Ok(());
+        };
+        let content = decode(
+            self.encryption.as_ref(),
+            TRASH_FILE,
+            read_blob(repository, &entry)?,
+        )?;
+        self.trash = serde_json::from_slice(&content)
+            .with_context(|| format!("failed to read {TRASH_FILE}"))?;
+        Ok(())
+    }
+
+    /// Store the trash in its [`TRASH_FILE`], or remove the file if the trash
+    /// is empty.
+    ///
+    /// The whole file is rewritten on every save, so it should stay small.
+    fn save_trash_to_tree(
+        &self,
+        repository: &Repository,
+        tree: &mut TreeBuilder<'_>,
+    ) -> anyhow::Result<()> {
+        if self.trash.is_empty() {
+            if tree.get(TRASH_FILE)?.is_some() {
+                tree.remove(TRASH_FILE)?;
+            }
+            return Ok(());
+        }
+        let content = encode(
+            self.encryption.as_ref(),
+            TRASH_FILE,
+            serde_json::to_vec(&self.trash)?,
+        )?;
+        add_blob_to_tree(repository, tree, TRASH_FILE.to_string(), &content)
+    }
+
+    /// Store the attachments of all notes at their [`attachment_path`],
+    /// including the notes in the trash.
+    ///
+    /// The attachments must have been written to the repository beforehand.
+    fn save_attachments_to_tree(
+        &self,
+        repository: &Repository,
+        tree: &mut TreeBuilder<'_>,
+    ) -> anyhow::Result<()> {
+        let mut hashes = BTreeMap::<&str, BTreeSet<&str>>::new();
+        let notes = self
+            .notes
+            .iter()
+            .chain(self.trash.iter().map(|it| &it.note));
+        for attachment in notes.flat_map(|it| &it.meta.attachments) {
+            let (prefix, rest) = split_hash(&attachment.hash)?;
+            hashes.entry(prefix).or_default().insert(rest);
+        }
+
+        let mut attachments_tree = repository.treebuilder(None)?;
+        let mut prefix_tree = repository.treebuilder(None)?;
+        for (prefix, rests) in hashes {
+            prefix_tree.clear()?;
+            for rest in rests {
+                let oid = Oid::from_str(&format!("{prefix}{rest}"))?;
+                prefix_tree.insert(rest, oid, FileMode::Blob.into())?;
+            }
+            add_tree_to_tree(&mut attachments_tree, &prefix_tree, prefix.to_string())?;
+        }
+        replace_tree_in_tree(tree, &attachments_tree, ATTACHMENTS_DIR.to_string())?;
+
+        Ok(())
+    }
+
+    /// Add the files of a note and its review to a day's tree.
+    fn add_note_to_tree(
+        &self,
+        repository: &Repository,
+        day_tree: &mut TreeBuilder<'_>,
+        note: &Note,
+    ) -> anyhow::Result<()> {
+        if let Some(review) = self.reviews.get(&note.id) {
+            let file = ReviewFile {
+                id: note.id,
+                review: review.clone(),
+            };
+            let filename = format!("{}{REVIEW_SUFFIX}", note.id);
+            let content = self.encode_file(&filename, serde_json::to_vec(&file)?)?;
+            add_blob_to_tree(repository, day_tree, filename, &content)?;
+        }
+
+        let filename = format!("{}{NOTE_SUFFIX}", note.id);
+        let content = self.encode_file(&filename, serde_json::to_vec(note)?)?;
+        add_blob_to_tree(repository, day_tree, filename, &content)?;
+
+        Ok(())
+    }
+
+    /// Whether [`Self::save_to_tree`] can update a tree instead of replacing
+    /// it. The tree must be the one the repo was loaded from.
+    pub fn can_update(&self, repository: &Repository, base: &Tree<'_>) -> anyhow::Result<bool> {
+        if self.dirty.is_none() {
+            return Ok(false);
+        }
+        // Rekeying changes every file.
+        let scheme = load_scheme(repository, base)?;
+        let unchanged = match (&scheme, &self.encryption) {
+            (None, None) => true,
+            (Some(old), Some(new)) => old.salt == new.scheme.salt && old.check == new.scheme.check,
+            _ => false,
+        };
+        Ok(unchanged)
+    }
+
+    /// Store every note in a directory based on its creation date. The review
+    /// state of a note is stored right next to it.
+    ///
+    /// If a base tree is given, the tree builder must have been created from
+    /// it and [`Self::can_update`] must hold. Only the directories containing
+    /// dirty notes are rewritten then, which is a lot faster for large repos.
+    pub fn save_to_tree(
+        mut self,
+        repository: &Repository,
+        base: Option<&Tree<'_>>,
+        tree: &mut TreeBuilder<'_>,
+    ) -> anyhow::Result<()> {
+        if let Some(encryption) = &self.encryption {
+            let content = serde_json::to_vec_pretty(&encryption.scheme)?;
+            add_blob_to_tree(repository, tree, ENCRYPTION_FILE.to_string(), &content)?;
+        }
+
+        self.save_attachments_to_tree(repository, tree)?;
+        self.save_trash_to_tree(repository, tree)?;
+
+        match (base, self.dirty.take()) {
+            (Some(base), Some(dirty)) => self.update_notes_in_tree(repository, base, tree, dirty),
+            _ => self.add_notes_to_tree(repository, tree),
+        }
+    }
+
+    fn add_notes_to_tree(
+        &mut self,
+        repository: &Repository,
+        tree: &mut TreeBuilder<'_>,
+    ) -> anyhow::Result<()> {
+        self.notes.sort_unstable_by_key(|it| it.id);
+
+        let mut year = 0;
+        let mut year_tree = repository.treebuilder(None)?;
+        let mut month = 0;
+        let mut month_tree = repository.treebuilder(None)?;
+        let mut day = 0;
+        let mut day_tree = repository.treebuilder(None)?;
+        let mut day_children = BTreeMap::new();
+
+        for note in &self.notes {
+            let time = note.id.time_utc();
+
+            if day != time.day() || month != time.month() || year != time.year() {
+                let path = day_path(year, month, day);
+                self.add_children_to_tree(repository, &mut day_tree, &path, &day_children)?;
+                add_tree_to_tree(&mut month_tree, &day_tree, format!("{day:02}"))?;
+                day_tree.clear()?;
+                day_children.clear();
+                day = time.day();
+            }
+
+            if month != time.month() || year != time.year() {
+                add_tree_to_tree(&mut year_tree, &month_tree, format!("{month:02}"))?;
+                month_tree.clear()?;
+                month = time.month();
+            }
+
+            if year != time.year() {
+                add_tree_to_tree(tree, &year_tree, format!("{year:04}"))?;
+                year_tree.clear()?;
+                year = time.year();
+            }
+
+            self.add_note_to_tree(repository, &mut day_tree, note)?;
+            day_children.insert(note.id, &note.children[..]);
+        }
+
+        let path = day_path(year, month, day);
+        self.add_children_to_tree(repository, &mut day_tree, &path, &day_children)?;
+        add_tree_to_tree(&mut month_tree, &day_tree, format!("{day:02}"))?;
+        add_tree_to_tree(&mut year_tree, &month_tree, format!("{month:02}"))?;
+        add_tree_to_tree(tree, &year_tree, format!("{year:04}"))?;
+
+        Ok(())
+    }
+
+    /// Rewrite only the day directories containing dirty notes, starting from
+    /// the base tree. All other directories are reused as they are.
+    fn update_notes_in_tree(
+        &self,
+        repository: &Repository,
+        base: &Tree<'_>,
+        tree: &mut TreeBuilder<'_>,
+        dirty: HashSet<NoteId>,
+    ) -> anyhow::Result<()> {
+        let notes = self
+            .notes
+            .iter()
+            .filter(|it| dirty.contains(&it.id))
+            .map(|it| (it.id, it))
+            .collect::<HashMap<_, _>>();
+
+        // The children files of the affected days must list all of their
+        // notes, not just the dirty ones.
+        let day_of = |id: NoteId| {
+            let time = id.time_utc();
+            (time.year(), time.month(), time.day())
+        };
+        let dirty_days = dirty.iter().map(|id| day_of(*id)).collect::<HashSet<_>>();
+        let mut children = HashMap::<_, BTreeMap<NoteId, &[NoteId]>>::new();
+        for note in &self.notes {
+            let key = day_of(note.id);
+            if dirty_days.contains(&key) {
+                children
+                    .entry(key)
+                    .or_default()
+                    .insert(note.id, &note.children);
+            }
+        }
+
+        let mut years = BTreeMap::<i16, BTreeMap<i8, BTreeMap<i8, Vec<NoteId>>>>::new();
+        for id in dirty {
+            let time = id.time_utc();
+            years
+                .entry(time.year())
+                .or_default()
+                .entry(time.month())
+                .or_default()
+                .entry(time.day())
+                .or_default()
+                .push(id);
+        }
+
+        for (year, months) in years {
+            let year_name = format!("{year:04}");
+            let base_year = find_subtree(repository, Some(base), &year_name)?;
+            let mut year_tree = repository.treebuilder(base_year.as_ref())?;
+
+            for (month, days) in months {
+                let month_name = format!("{month:02}");
+                let base_month = find_subtree(repository, base_year.as_ref(), &month_name)?;
+                let mut month_tree = repository.treebuilder(base_month.as_ref())?;
+
+                for (day, ids) in days {
+                    let day_name = format!("{day:02}");
+                    let base_day = find_subtree(repository, base_month.as_ref(), &day_name)?;
+                    let mut day_tree = repository.treebuilder(base_day.as_ref())?;
+
+                    for id in ids {
+                        for filename in
+                            [format!("{id}{NOTE_SUFFIX}"), format!("{id}{REVIEW_SUFFIX}")]
+                        {
+                            if day_tree.get(&filename)?.is_some() {
+                                day_tree.remove(&filename)?;
+                            }
+                        }
+                        if let Some(note) = notes.get(&id) {
+                            self.add_note_to_tree(repository, &mut day_tree, note)?;
+                        }
+                    }
+
+                    let path = day_path(year, month, day);
+                    let day_children = children.remove(&(year, month, day)).unwrap_or_default();
+                    self.add_children_to_tree(repository, &mut day_tree, &path, &day_children)?;
+
+                    replace_tree_in_tree(&mut month_tree, &day_tree, day_name)?;
+                }
+
+                replace_tree_in_tree(&mut year_tree, &month_tree, month_name)?;
+            }
+
+            replace_tree_in_tree(tree, &year_tree, year_name)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn migrate(self) -> Self {
+        self
+    }
+
+    /// The ids of all notes, including those in the trash.
+    pub fn ids(&self) -> HashSet<NoteId> {
+        let notes = self.notes.iter().map(|note| note.id);
+        let trash = self.trash.iter().map(|trashed| trashed.note.id);
+        notes.chain(trash).collect()
+    }
+}
+
+/// A repo whose notes are only read when they are needed.
+///
+/// Loading it only reads the tree and the [`CHILDREN_FILE`] of every day, so
+/// the structure of the notes is known up front. Days without such a file,
+/// for example because they were written by an older version, fall back to
+/// reading their notes.
+pub struct LazyRepo {
+    repository: Repository,
+    encryption: Option<Encryption>,
+    notes: HashMap<NoteId, Oid>,
+    reviews: HashMap<NoteId, Oid>,
+    children: HashMap<NoteId, Vec<NoteId>>,
+}
+
+impl LazyRepo {
+    /// Read the structure of a tree, or create an empty repo without a tree.
+    ///
+    /// If the tree is encrypted, a secret is required to unlock it.
+    pub fn load(
+        repository: Repository,
+        tree: Option<Oid>,
+        secret: Option<&Secret>,
+    ) -> anyhow::Result<Self> {
+        let mut result = Self {
+            repository,
+            encryption: None,
+            notes: HashMap::new(),
+            reviews: HashMap::new(),
+            children: HashMap::new(),
+        };
+        if let Some(tree) = tree {
+            result.load_tree(tree, secret)?;
+        }
+        Ok(result)
+    }
+
+    fn load_tree(&mut self, tree: Oid, secret: Option<&Secret>) -> anyhow::Result<()> {
+        let tree = self.repository.find_tree(tree)?;
+        self.encryption = unlock(&self.repository, &tree, secret)?;
+
+        // The notes of each directory, and its children file if it has one.
+        let mut dirs = HashMap::<String, (Vec<NoteId>, Option<Oid>)>::new();
+        tree.walk(TreeWalkMode::PreOrder, |path, entry| {
+            if path.is_empty() && entry.name() == Some(ATTACHMENTS_DIR) {
+                return TreeWalkResult::Skip;
+            }
+            let Some(name) = entry.name() else {
+                return TreeWalkResult::Ok;
+            };
+            if name == CHILDREN_FILE {
+                dirs.entry(path.to_string()).or_default().1 = Some(entry.id());
+            } else if let Some(id) = name.strip_suffix(REVIEW_SUFFIX) {
+                if let Ok(id) = id.parse() {
+                    self.reviews.insert(id, entry.id());
+                }
+            } else if let Some(id) = name.strip_suffix(NOTE_SUFFIX)
+                && let Ok(id) = id.parse()
+            {
+                self.notes.insert(id, entry.id());
+                dirs.entry(path.to_string()).or_default().0.push(id);
+            }
+            TreeWalkResult::Ok
+        })?;
+
+        for (path, (ids, children_file)) in dirs {
+            match children_file {
+                Some(oid) => {
+                    // The path passed to the walk callback ends with a slash.
+                    let name = format!("{path}{CHILDREN_FILE}");
+                    let content = self.repository.find_blob(oid)?.content().to_vec();
+                    let content = decode(self.encryption.as_ref(), &name, content)?;
+                    let children = serde_json::from_slice::<HashMap<NoteId, Vec<NoteId>>>(&content)
+                        .with_context(|| format!("failed to read {name}"))?;
+                    self.children.extend(children);
+                }
+                None => {
+                    for id in ids {
+                        if let Some(note) = self.load_note(id)? {
+                            self.children.insert(id, note.children);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Children files may be outdated if a note was removed by hand.
+        self.children.retain(|id, _| self.notes.contains_key(id));
+
+        Ok(())
+    }
+
+    /// The ids of all notes, in no particular order.
+    pub fn ids(&self) -> impl Iterator<Item = NoteId> + '_ {
+        self.notes.keys().copied()
+    }
+
+    /// The children of a note, read without reading the note itself.
+    pub fn children(&self, id: NoteId) -> &[NoteId] {
+        self.children.get(&id).map_or(&[], |it| it)
+    }
+
+    fn read(&self, oid: Oid, name: &str) -> anyhow::Result<Vec<u8>> {
+        let content = self.repository.find_blob(oid)?.content().to_vec();
+        decode(self.encryption.as_ref(), name, content)
+    }
+
+    /// Read a note from the repository.
+    pub fn load_note(&self, id: NoteId) -> anyhow::Result<Option<Note>> {
+        let Some(oid) = self.notes.get(&id) else {
+            return Ok(None);
+        };
+        let content = self.read(*oid, &format!("{id}{NOTE_SUFFIX}"))?;
+        Ok(Some(serde_json::from_slice(&content)?))
+    }
+
+    /// Read the review state of a note from the repository.
+    pub fn load_review(&self, id: NoteId) -> anyhow::Result<Option<Review>> {
+        let Some(oid) = self.reviews.get(&id) else {
+            return Ok(None);
+        };
+        let content = self.read(*oid, &format!("{id}{REVIEW_SUFFIX}"))?;
+        let file = serde_json::from_slice::<ReviewFile>(&content)?;
+        Ok(Some(file.review))
+    }
+}
@@ -0,0 +1,169 @@
+use jiff::{SignedDuration, Timestamp};
+
+use crate::{
+    ids::NoteId,
+    repo::{Grade, Review, ReviewRecord},
+    store::Store,
+};
+
+/// Separates question and answer in a single note, as in `question::answer`.
+pub const SEPARATOR: &str = "::";
+
+/// The ease a card starts out with, in permille.
+pub const INITIAL_EASE: u32 = 2500;
+
+/// The ease never drops below this value, in permille.
+pub const MIN_EASE: u32 = 1300;
+
+/// How long to wait before showing a forgotten card again.
+pub const RELEARN_DELAY: SignedDuration = SignedDuration::from_mins(10);
+
+/// The interval never grows beyond this many days, about a hundred years.
+pub const MAX_INTERVAL: u32 = 36500;
+
+/// A flashcard derived from a note.
+pub struct Card {
+    pub id: NoteId,
+    pub front: String,
+    pub back: String,
+}
+
+impl Card {
+    /// Turn a note into a card, if it is marked as one.
+    ///
+    /// A note is a card if its text has the form `question::answer`, or if its
+    /// text ends with a `?` and it has children, in which case the children
+    /// are the answer.
+    pub fn from_note(store: &Store, id: NoteId) -> Option<Self> {
+        let note = store.get(id)?;
+
+        if let Some((front, back)) = note.text.split_once(SEPARATOR) {
+            let (front, back) = (front.trim(), back.trim());
+            if front.is_empty() || back.is_empty() {
+                return None;
+            }
+            return Some(Self {
+                id,
+                front: front.to_string(),
+                back: back.to_string(),
+            });
+        }
+
+        let front = note.text.trim();
+        if !front.ends_with('?') || note.children.is_empty() {
+            return None;
+        }
+
+        let back = note
+            .children
+            .iter()
+            .filter_map(|child| store.get(*child))
+            .map(|child| child.text)
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(Self {
+            id,
+            front: front.to_string(),
+            back,
+        })
+    }
+}
+
+/// Find all notes that are marked as cards, sorted by id.
+///
+/// See [`Card::from_note`] for how notes are marked as cards.
+pub fn cards(store: &Store) -> Vec<Card> {
+    let mut ids = store.ids().collect::<Vec<_>>();
+    ids.sort_unstable();
+    ids.into_iter()
+        .filter_map(|id| Card::from_note(store, id))
+        .collect()
+}
+
+/// The cards that should be reviewed at the given time.
+///
+/// Cards that are due come first, the most overdue one first. They are
+/// followed by at most `new_limit` cards that were never reviewed before,
+/// oldest first.
+pub fn due_cards(store: &Store, now: Timestamp, new_limit: usize) -> Vec<Card> {
+    let mut due = vec![];
+    let mut new = vec![];
+    for card in cards(store) {
+        match store.review(card.id) {
+            Some(review) if review.due <= now => due.push((review.due, card)),
+            Some(_) => {}
+            None => new.push(card),
+        }
+    }
+
+    due.sort_by_key(|(due, card)| (*due, card.id));
+    due.into_iter()
+        .map(|(_, card)| card)
+        .chain(new.into_iter().take(new_limit))
+        .collect()
+}
+
+/// The SM-2 quality of a grade, on a scale from 0 to 5.
+fn quality(grade: Grade) -> u32 {
+    match grade {
+        Grade::Again => 1,
+        Grade::Hard => 3,
+        Grade::Good => 4,
+        Grade::Easy => 5,
+    }
+}
+
+/// The time a card is due after waiting for `interval`, or the latest possible
+/// time if that is out of range.
+fn due_after(now: Timestamp, interval: SignedDuration) -> Timestamp {
+    now.checked_add(interval).unwrap_or(Timestamp::MAX)
+}
+
+/// Compute the next review state of a card using the SM-2 algorithm.
+///
+/// A card that is forgotten starts over with an interval of one day after
+/// being relearned shortly, and its ease decreases. Otherwise, the interval is
+/// one day after the first review, six days after the second, and grows by
+/// the card's ease after that, up to [`MAX_INTERVAL`].
+pub fn schedule(prev: Option<&Review>, grade: Grade, now: Timestamp) -> Review {
+    let mut review = prev.cloned().unwrap_or_else(|| Review {
+        due: now,
+        interval: 0,
+        ease: INITIAL_EASE,
+        repetitions: 0,
+        lapses: 0,
+        history: vec![],
+    });
+
+    let q = quality(grade);
+    if q < 3 {
+        if review.repetitions > 0 {
+            review.lapses += 1;
+        }
+        review.repetitions = 0;
+        review.interval = 1;
+        review.due = due_after(now, RELEARN_DELAY);
+    } else {
+        review.interval = match review.repetitions {
+            0 => 1,
+            1 => 6,
+            _ => {
+                let interval = (u64::from(review.interval) * u64::from(review.ease)).div_ceil(1000);
+                interval.min(u64::from(MAX_INTERVAL)) as u32
+            }
+        };
+        review.repetitions += 1;
+        let interval = SignedDuration::from_hours(24 * i64::from(review.interval));
+        review.due = due_after(now, interval);
+    }
+
+    // EF' = EF + (0.1 - (5 - q) * (0.08 + (5 - q) * 0.02)), in permille
+    let miss = 5 - q;
+    let delta = 100 - i64::from(miss * (80 + miss * 20));
+    let ease = (i64::from(review.ease) + delta).max(i64::from(MIN_EASE));
+    review.ease = u32::try_from(ease).unwrap_or(u32::MAX);
+
+    review.history.push(ReviewRecord { time: now, grade });
+    review
+}
@@ -1,14 +1,30 @@
-use std::collections::{HashMap, HashSet};
+mod lazy;
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use git2::Oid;
+use jiff::{SignedDuration, Timestamp};
 
 use crate::{
+    crypto::Encryption,
     ids::NoteId,
-    repo::{Note, Repo},
+    links,
+    repo::{Attachment, Grade, Metadata, Note, Repo, Review, TrashedNote, TrashedParent},
+    review, tags,
 };
 
+pub use self::lazy::LazyStore;
+
+/// How long deleted notes are kept in the trash.
+///
+/// Older notes are purged whenever a store is loaded, see [`Store::load`].
+pub const TRASH_RETENTION: SignedDuration = SignedDuration::from_hours(30 * 24);
+
 #[derive(Clone)]
 pub struct RawNote {
     pub text: String,
     pub children: Vec<NoteId>,
+    pub meta: Metadata,
 }
 
 impl RawNote {
@@ -16,6 +32,7 @@ impl RawNote {
         Self {
             text: note.text,
             children: note.children,
+            meta: note.meta,
         }
     }
 
@@ -24,6 +41,97 @@ impl RawNote {
             id,
             text: self.text,
             children: self.children,
+            meta: self.meta,
+        }
+    }
+
+    fn touch(&mut self) {
+        self.meta.modified = Some(Timestamp::now());
+    }
+}
+
+/// A deleted note, see [`Store::delete`].
+#[derive(Clone)]
+pub struct Trashed {
+    pub note: RawNote,
+    pub review: Option<Review>,
+    pub deleted: Timestamp,
+    /// Where the note used to be a child, see [`Store::restore`].
+    pub parents: Vec<TrashedParent>,
+}
+
+impl Trashed {
+    fn load(trashed: TrashedNote) -> (NoteId, Self) {
+        let id = trashed.note.id;
+        let result = Self {
+            note: RawNote::load(trashed.note),
+            review: trashed.review,
+            deleted: trashed.deleted,
+            parents: trashed.parents,
+        };
+        (id, result)
+    }
+
+    fn save(self, id: NoteId) -> TrashedNote {
+        TrashedNote {
+            deleted: self.deleted,
+            parents: self.parents,
+            note: self.note.save(id),
+            review: self.review,
+        }
+    }
+}
+
+/// A note and its descendants, taken out of a store.
+///
+/// See [`Store::extract_subtree`] and [`Store::insert_subtree`].
+pub struct Subtree {
+    pub root: NoteId,
+    pub notes: Vec<Note>,
+    pub reviews: HashMap<NoteId, Review>,
+}
+
+impl Subtree {
+    /// Give every note a new id, keeping the structure of the subtree intact.
+    ///
+    /// The new ids are distinct from each other, from the original ids and
+    /// from all ids for which `is_used` returns true, usually those of the
+    /// store the subtree will be inserted into, see [`Store::is_used`].
+    ///
+    /// Links in the texts still point to the original notes, and review
+    /// states are dropped since they belong to the original notes.
+    pub fn with_fresh_ids(self, is_used: impl Fn(NoteId) -> bool) -> Self {
+        let old = self
+            .notes
+            .iter()
+            .map(|note| note.id)
+            .collect::<HashSet<_>>();
+        let mut ids = HashMap::new();
+        let mut new = HashSet::new();
+        for note in &self.notes {
+            let id = NoteId::new_unused(|id| is_used(id) || old.contains(&id) || new.contains(&id));
+            new.insert(id);
+            ids.insert(note.id, id);
+        }
+
+        let notes = self
+            .notes
+            .into_iter()
+            .map(|note| Note {
+                id: ids[&note.id],
+                children: note
+                    .children
+                    .iter()
+                    .map(|child| ids.get(child).copied().unwrap_or(*child))
+                    .collect(),
+                ..note
+            })
+            .collect();
+
+        Self {
+            root: ids[&self.root],
+            notes,
+            reviews: HashMap::new(),
         }
     }
 }
@@ -34,13 +142,124 @@ pub struct RichNote {
     pub text: String,
     pub children: Vec<NoteId>,
     pub parents: HashSet<NoteId>,
+    /// The notes linked to in the text, see [`links::parse`].
+    pub links: Vec<NoteId>,
+    /// The notes linking to this note in their text.
+    pub backlinks: HashSet<NoteId>,
+    pub meta: Metadata,
+}
+
+/// Lookup tables derived from the notes.
+///
+/// They are updated note by note whenever a note changes, so a single change
+/// doesn't require looking at every note.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct Index {
+    /// How often each note appears as a child of each of its parents.
+    parents: HashMap<NoteId, HashMap<NoteId, usize>>,
+    tags: HashMap<String, HashSet<NoteId>>,
+    backlinks: HashMap<NoteId, HashSet<NoteId>>,
+}
+
+impl Index {
+    fn add_parent(&mut self, child: NoteId, parent: NoteId) {
+        *self
+            .parents
+            .entry(child)
+            .or_default()
+            .entry(parent)
+            .or_default() += 1;
+    }
+
+    fn remove_parent(&mut self, child: NoteId, parent: NoteId) {
+        let Some(parents) = self.parents.get_mut(&child) else {
+            return;
+        };
+        if let Some(count) = parents.get_mut(&parent) {
+            *count -= 1;
+            if *count == 0 {
+                parents.remove(&parent);
+            }
+        }
+        if parents.is_empty() {
+            self.parents.remove(&child);
+        }
+    }
+
+    fn add_text(&mut self, id: NoteId, text: &str) {
+        for tag in tags::parse(text) {
+            self.tags.entry(tag.to_string()).or_default().insert(id);
+        }
+
+        // Links to notes that don't exist are kept, see
+        // [`Store::dangling_links`].
+        for target in links::parse(text) {
+            self.backlinks.entry(target).or_default().insert(id);
+        }
+    }
+
+    fn remove_text(&mut self, id: NoteId, text: &str) {
+        for tag in tags::parse(text) {
+            if let Some(ids) = self.tags.get_mut(tag) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.tags.remove(tag);
+                }
+            }
+        }
+
+        for target in links::parse(text) {
+            if let Some(ids) = self.backlinks.get_mut(&target) {
+                ids.remove(&id);
+                if ids.is_empty() {
+                    self.backlinks.remove(&target);
+                }
+            }
+        }
+    }
+
+    fn add(&mut self, id: NoteId, note: &RawNote) {
+        for child in &note.children {
+            self.add_parent(*child, id);
+        }
+        self.add_text(id, &note.text);
+    }
+
+    fn remove(&mut self, id: NoteId, note: &RawNote) {
+        for child in &note.children {
+            self.remove_parent(*child, id);
+        }
+        self.remove_text(id, &note.text);
+    }
+}
+
+/// Everything a store knows about a note id, see [`Journal`].
+struct Saved {
+    note: Option<RawNote>,
+    review: Option<Review>,
+    trashed: Option<Trashed>,
+    dirty: bool,
 }
 
+/// The state of all notes touched during a [`Store::transaction`] from before
+/// they were first touched, so the transaction can be rolled back.
+type Journal = HashMap<NoteId, Saved>;
+
 #[derive(Default)]
 pub struct Store {
     id: u64,
     notes: HashMap<NoteId, RawNote>,
-    parents: HashMap<NoteId, HashMap<NoteId, usize>>,
+    index: Index,
+    reviews: HashMap<NoteId, Review>,
+    trash: HashMap<NoteId, Trashed>,
+    /// Passed through unchanged from loading to saving.
+    encryption: Option<Encryption>,
+    /// The commit the store was loaded from or last saved to.
+    base: Option<Oid>,
+    /// The notes modified since [`Self::base`], see [`Repo::dirty`].
+    dirty: HashSet<NoteId>,
+    /// Only present during a [`Self::transaction`].
+    journal: Option<Journal>,
 }
 
 impl Store {
@@ -48,6 +267,8 @@ impl Store {
         Self::default()
     }
 
+    /// Create a store from a repo, purging notes that have been in the trash
+    /// for longer than [`TRASH_RETENTION`].
     pub fn load(repo: Repo) -> Self {
         let notes = repo
             .notes
@@ -55,14 +276,25 @@ impl Store {
             .map(|note| (note.id, RawNote::load(note)))
             .collect::<HashMap<_, _>>();
 
+        let trash = repo.trash.into_iter().map(Trashed::load).collect();
+
         let mut result = Self {
             notes,
+            reviews: repo.reviews,
+            trash,
+            encryption: repo.encryption,
+            base: repo.base,
             ..Self::default()
         };
+        result.purge_trash_before(Timestamp::now() - TRASH_RETENTION);
         result.make_consistent_and_tick();
         result
     }
 
+    /// Create a repo from the store.
+    ///
+    /// Once the repo has been committed, call [`Self::saved`] so the next save
+    /// only writes the notes modified in the meantime.
     pub fn save(&self) -> Repo {
         let notes = self
             .notes
@@ -70,17 +302,101 @@ impl Store {
             .map(|(id, note)| note.clone().save(*id))
             .collect::<Vec<_>>();
 
-        Repo { notes }
+        // Sorted so the trash file only changes when the trash does.
+        let mut trash = self
+            .trash
+            .iter()
+            .map(|(id, trashed)| trashed.clone().save(*id))
+            .collect::<Vec<_>>();
+        trash.sort_unstable_by_key(|it| it.note.id);
+
+        Repo {
+            notes,
+            reviews: self.reviews.clone(),
+            trash,
+            encryption: self.encryption.clone(),
+            base: self.base,
+            dirty: Some(self.dirty.clone()),
+        }
+    }
+
+    /// Mark the current state of the store as committed as `oid`.
+    ///
+    /// Must not be called during a [`Self::transaction`], since rolling it back
+    /// would restore notes that were not committed.
+    pub fn saved(&mut self, oid: Oid) {
+        debug_assert!(self.journal.is_none(), "saved during a transaction");
+        self.base = Some(oid);
+        self.dirty.clear();
     }
 
     pub fn id(&self) -> u64 {
         self.id
     }
 
+    /// The ids of all notes, in no particular order.
+    pub fn ids(&self) -> impl Iterator<Item = NoteId> + '_ {
+        self.notes.keys().copied()
+    }
+
+    /// The ids of all notes without parents, sorted by id.
+    pub fn roots(&self) -> Vec<NoteId> {
+        let mut roots = self
+            .notes
+            .keys()
+            .filter(|id| !self.index.parents.contains_key(id))
+            .copied()
+            .collect::<Vec<_>>();
+        roots.sort_unstable();
+        roots
+    }
+
+    /// All tags used by at least one note, sorted alphabetically.
+    pub fn tags(&self) -> Vec<&str> {
+        let mut tags = self
+            .index
+            .tags
+            .keys()
+            .map(|it| it.as_str())
+            .collect::<Vec<_>>();
+        tags.sort_unstable();
+        tags
+    }
+
+    /// The ids of all notes with a tag, sorted by id.
+    pub fn notes_by_tag(&self, tag: &str) -> Vec<NoteId> {
+        let mut ids = self
+            .index
+            .tags
+            .get(tag)
+            .map(|ids| ids.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        ids.sort_unstable();
+        ids
+    }
+
+    /// All links to notes that don't exist, as pairs of the linking note and
+    /// the missing note, sorted by the linking note.
+    ///
+    /// Deleting a note doesn't touch the links to it, so they can be found
+    /// here and fixed by hand.
+    pub fn dangling_links(&self) -> Vec<(NoteId, NoteId)> {
+        let mut result = self
+            .index
+            .backlinks
+            .iter()
+            .filter(|(target, _)| !self.notes.contains_key(target))
+            .flat_map(|(target, sources)| sources.iter().map(|source| (*source, *target)))
+            .collect::<Vec<_>>();
+        result.sort_unstable();
+        result
+    }
+
     pub fn get(&self, id: NoteId) -> Option<RichNote> {
         let info = self.notes.get(&id)?;
 
         let parents = self
+            .index
             .parents
             .get(&id)
             .map(|ps| ps.keys().copied().collect::<HashSet<_>>())
@@ -91,75 +407,395 @@ impl Store {
             text: info.text.clone(),
             children: info.children.clone(),
             parents,
+            links: links::parse(&info.text),
+            backlinks: self.index.backlinks.get(&id).cloned().unwrap_or_default(),
+            meta: info.meta.clone(),
         })
     }
 
+    pub fn review(&self, id: NoteId) -> Option<&Review> {
+        self.reviews.get(&id)
+    }
+
     fn tick(&mut self) {
         self.id += 1;
     }
 
+    /// Fix inconsistencies between notes and rebuild the index.
+    ///
+    /// This looks at every note, so operations on single notes update the
+    /// index incrementally instead.
     fn make_consistent_and_tick(&mut self) {
         // Remove child notes that don't exist
         let children = self.notes.keys().copied().collect::<HashSet<_>>();
-        for info in &mut self.notes.values_mut() {
+        for (id, info) in &mut self.notes {
+            let len = info.children.len();
             info.children.retain(|child| children.contains(child));
+            if info.children.len() != len {
+                self.dirty.insert(*id);
+            }
         }
 
-        // Update parents to match new child notes
-        self.parents.clear();
+        // Rebuild the index from scratch
+        self.index = Index::default();
         for (id, info) in &self.notes {
-            for child in &info.children {
-                *self
-                    .parents
-                    .entry(*child)
-                    .or_default()
-                    .entry(*id)
-                    .or_default() += 1;
-            }
+            self.index.add(*id, info);
         }
 
+        // Remove review state of notes that don't exist
+        self.reviews.retain(|id, _| {
+            let exists = self.notes.contains_key(id);
+            if !exists {
+                self.dirty.insert(*id);
+            }
+            exists
+        });
+
         self.tick();
     }
 
+    /// Remember the state of a note before it is first modified during a
+    /// transaction.
+    ///
+    /// Must be called before modifying the note, its review state, its trash
+    /// entry or whether it is dirty.
+    fn journal(&mut self, id: NoteId) {
+        let Some(journal) = &mut self.journal else {
+            return;
+        };
+        journal.entry(id).or_insert_with(|| Saved {
+            note: self.notes.get(&id).cloned(),
+            review: self.reviews.get(&id).cloned(),
+            trashed: self.trash.get(&id).cloned(),
+            dirty: self.dirty.contains(&id),
+        });
+    }
+
+    /// Whether a note with this id exists, either in the store or the trash.
+    pub fn is_used(&self, id: NoteId) -> bool {
+        self.notes.contains_key(&id) || self.trash.contains_key(&id)
+    }
+
     pub fn create(&mut self, text: String) -> NoteId {
-        let id = NoteId::new();
+        let id = NoteId::new_unused(|id| self.is_used(id));
         let note = RawNote {
             text,
             children: vec![],
+            meta: Metadata {
+                modified: Some(Timestamp::now()),
+                device: gethostname::gethostname().into_string().ok(),
+                ..Metadata::default()
+            },
         };
 
+        self.journal(id);
+        self.index.add(id, &note);
         self.notes.insert(id, note);
-        self.make_consistent_and_tick();
+        self.dirty.insert(id);
+        self.tick();
 
         id
     }
 
-    pub fn delete(&mut self, id: NoteId) -> Option<RawNote> {
+    /// Move a note to the trash and remove it from the children of its
+    /// parents, remembering where it was.
+    fn move_to_trash(&mut self, id: NoteId, deleted: Timestamp) -> Option<RawNote> {
+        self.journal(id);
         let info = self.notes.remove(&id)?;
-        self.make_consistent_and_tick();
+        self.index.remove(id, &info);
+
+        let mut parents = self
+            .index
+            .parents
+            .remove(&id)
+            .unwrap_or_default()
+            .into_keys()
+            .collect::<Vec<_>>();
+        parents.sort_unstable();
+
+        let mut positions = vec![];
+        for parent in parents {
+            self.journal(parent);
+            let note = self.notes.get_mut(&parent).unwrap();
+            positions.extend(
+                note.children
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, child)| **child == id)
+                    .map(|(index, _)| TrashedParent { id: parent, index }),
+            );
+            note.children.retain(|child| *child != id);
+            self.dirty.insert(parent);
+        }
+
+        let trashed = Trashed {
+            note: info.clone(),
+            review: self.reviews.remove(&id),
+            deleted,
+            parents: positions,
+        };
+        self.trash.insert(id, trashed);
+        self.dirty.insert(id);
+        Some(info)
+    }
+
+    /// Delete a note and remove it from the children of its parents.
+    ///
+    /// The note is moved to the trash, from where it can be restored with
+    /// [`Self::restore`].
+    pub fn delete(&mut self, id: NoteId) -> Option<RawNote> {
+        let info = self.move_to_trash(id, Timestamp::now())?;
+        self.tick();
         Some(info)
     }
 
+    /// All notes in the trash, sorted by when they were deleted.
+    pub fn trash(&self) -> Vec<(NoteId, &Trashed)> {
+        let mut result = self
+            .trash
+            .iter()
+            .map(|(id, trashed)| (*id, trashed))
+            .collect::<Vec<_>>();
+        result.sort_unstable_by_key(|(id, trashed)| (trashed.deleted, *id));
+        result
+    }
+
+    /// Move a note from the trash back into the store.
+    ///
+    /// The note becomes a child of its previous parents again, at its previous
+    /// index if possible. The same goes for its children, so a subtree can be
+    /// restored note by note in any order. Parents and children that don't
+    /// exist any more are ignored. Fails if the note isn't in the trash or a
+    /// note with the same id exists.
+    pub fn restore(&mut self, id: NoteId) -> Option<()> {
+        if self.notes.contains_key(&id) || !self.trash.contains_key(&id) {
+            return None;
+        }
+        self.journal(id);
+        let Trashed {
+            mut note,
+            review,
+            parents,
+            ..
+        } = self.trash.remove(&id)?;
+
+        // Children still in the trash become children again once they are
+        // restored themselves.
+        for (index, child) in note.children.iter().enumerate() {
+            if self.trash.contains_key(child) {
+                self.journal(*child);
+                let trashed = self.trash.get_mut(child).unwrap();
+                trashed.parents.push(TrashedParent { id, index });
+            }
+        }
+        note.children
+            .retain(|child| *child == id || self.notes.contains_key(child));
+        self.index.add(id, &note);
+        self.notes.insert(id, note);
+        if let Some(review) = review {
+            self.reviews.insert(id, review);
+        }
+        self.dirty.insert(id);
+
+        // The indices are sorted, so inserting them in order restores the
+        // previous positions if the parent didn't change in the meantime.
+        for TrashedParent { id: parent, index } in parents {
+            if !self.notes.contains_key(&parent) {
+                continue;
+            }
+            self.journal(parent);
+            let note = self.notes.get_mut(&parent).unwrap();
+            let index = index.min(note.children.len());
+            note.children.insert(index, id);
+            note.touch();
+            self.index.add_parent(id, parent);
+            self.dirty.insert(parent);
+        }
+
+        self.tick();
+        Some(())
+    }
+
+    /// Permanently delete a note from the trash.
+    pub fn purge(&mut self, id: NoteId) -> Option<Trashed> {
+        self.journal(id);
+        let trashed = self.trash.remove(&id)?;
+        self.tick();
+        Some(trashed)
+    }
+
+    /// Permanently delete all notes that were moved to the trash before a
+    /// point in time. Returns the number of purged notes.
+    pub fn purge_trash_before(&mut self, time: Timestamp) -> usize {
+        let ids = self
+            .trash
+            .iter()
+            .filter(|(_, trashed)| trashed.deleted < time)
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        for id in &ids {
+            self.journal(*id);
+            self.trash.remove(id);
+        }
+        if !ids.is_empty() {
+            self.tick();
+        }
+        ids.len()
+    }
+
+    /// Permanently delete all notes in the trash. Returns the number of purged
+    /// notes.
+    pub fn empty_trash(&mut self) -> usize {
+        let purged = self.trash.len();
+        let ids = self.trash.keys().copied().collect::<Vec<_>>();
+        for id in ids {
+            self.journal(id);
+        }
+        self.trash.clear();
+        if purged > 0 {
+            self.tick();
+        }
+        purged
+    }
+
     pub fn set_text(&mut self, id: NoteId, text: String) -> Option<()> {
-        let note = self.notes.get_mut(&id)?;
-        if note.text == text {
+        if self.notes.get(&id)?.text == text {
             return None;
         }
+        self.journal(id);
+        let note = self.notes.get_mut(&id).unwrap();
+        self.index.remove_text(id, &note.text);
         note.text = text;
+        self.index.add_text(id, &note.text);
+        note.touch();
+        self.dirty.insert(id);
         self.tick();
         Some(())
     }
 
-    pub fn set_children(&mut self, id: NoteId, children: Vec<NoteId>) -> Option<()> {
-        let note = self.notes.get_mut(&id)?;
-        if note.children == children {
+    /// Replace a tag with a different tag in the text of all notes.
+    ///
+    /// If the new tag is already in use, the two tags are merged. Returns the
+    /// number of notes that were modified.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> usize {
+        let ids = self.notes_by_tag(from);
+        if from == to || ids.is_empty() {
+            return 0;
+        }
+        for id in &ids {
+            self.journal(*id);
+            let note = self.notes.get_mut(id).unwrap();
+            self.index.remove_text(*id, &note.text);
+            note.text = tags::rename(&note.text, from, to);
+            self.index.add_text(*id, &note.text);
+            note.touch();
+            self.dirty.insert(*id);
+        }
+        self.tick();
+        ids.len()
+    }
+
+    /// Replace the children of a note. Children that don't exist are ignored.
+    pub fn set_children(&mut self, id: NoteId, mut children: Vec<NoteId>) -> Option<()> {
+        children.retain(|child| self.notes.contains_key(child));
+        if self.notes.get(&id)?.children == children {
             return None;
         }
+        self.journal(id);
+        let note = self.notes.get_mut(&id).unwrap();
+        for child in &note.children {
+            self.index.remove_parent(*child, id);
+        }
         note.children = children;
-        self.make_consistent_and_tick();
+        for child in &note.children {
+            self.index.add_parent(*child, id);
+        }
+        note.touch();
+        self.dirty.insert(id);
+        self.tick();
         Some(())
     }
 
+    pub fn property(&self, id: NoteId, key: &str) -> Option<&str> {
+        let note = self.notes.get(&id)?;
+        note.meta.properties.get(key).map(|it| it.as_str())
+    }
+
+    /// Set a property of a note, or remove it if the value is [`None`].
+    ///
+    /// Returns `Some(())` if the note was modified.
+    pub fn set_property(&mut self, id: NoteId, key: String, value: Option<String>) -> Option<()> {
+        self.journal(id);
+        let note = self.notes.get_mut(&id)?;
+        let properties = &mut note.meta.properties;
+        match value {
+            Some(value) if properties.get(&key) != Some(&value) => {
+                properties.insert(key, value);
+            }
+            None if properties.contains_key(&key) => {
+                properties.remove(&key);
+            }
+            _ => return None,
+        }
+        note.touch();
+        self.dirty.insert(id);
+        self.tick();
+        Some(())
+    }
+
+    pub fn attachment(&self, id: NoteId, name: &str) -> Option<&Attachment> {
+        let note = self.notes.get(&id)?;
+        note.meta.attachments.iter().find(|it| it.name == name)
+    }
+
+    /// Attach a file to a note, replacing any attachment with the same name.
+    ///
+    /// Returns `Some(())` if the note was modified.
+    pub fn attach(&mut self, id: NoteId, attachment: Attachment) -> Option<()> {
+        self.journal(id);
+        let note = self.notes.get_mut(&id)?;
+        let attachments = &mut note.meta.attachments;
+        match attachments.iter_mut().find(|it| it.name == attachment.name) {
+            Some(existing) if *existing == attachment => return None,
+            Some(existing) => *existing = attachment,
+            None => attachments.push(attachment),
+        }
+        note.touch();
+        self.dirty.insert(id);
+        self.tick();
+        Some(())
+    }
+
+    /// Remove an attachment from a note.
+    ///
+    /// Returns the removed attachment if there was one.
+    pub fn detach(&mut self, id: NoteId, name: &str) -> Option<Attachment> {
+        self.journal(id);
+        let note = self.notes.get_mut(&id)?;
+        let attachments = &mut note.meta.attachments;
+        let index = attachments.iter().position(|it| it.name == name)?;
+        let attachment = attachments.remove(index);
+        note.touch();
+        self.dirty.insert(id);
+        self.tick();
+        Some(attachment)
+    }
+
+    /// Record a review of a note and schedule its next review.
+    ///
+    /// Returns the new review state if the note exists.
+    pub fn record_review(&mut self, id: NoteId, grade: Grade, time: Timestamp) -> Option<Review> {
+        if !self.notes.contains_key(&id) {
+            return None;
+        }
+        self.journal(id);
+        let review = review::schedule(self.reviews.get(&id), grade, time);
+        self.reviews.insert(id, review.clone());
+        self.dirty.insert(id);
+        self.tick();
+        Some(review)
+    }
+
     /// Find the index of a child based on its id and iteration.
     ///
     /// The index returned is in the range `[0, note.children.len())`.
@@ -211,11 +847,18 @@ impl Store {
         child_id: NoteId,
         child_position: isize,
     ) -> Option<()> {
+        let child_exists = self.notes.contains_key(&child_id);
+        self.journal(id);
         let note = self.notes.get_mut(&id)?;
-        let index = Self::resolve_child_position(&note.children, child_position);
-        note.children.insert(index, child_id);
+        if child_exists {
+            let index = Self::resolve_child_position(&note.children, child_position);
+            note.children.insert(index, child_id);
+            self.index.add_parent(child_id, id);
+        }
+        note.touch();
+        self.dirty.insert(id);
 
-        self.make_consistent_and_tick();
+        self.tick();
         Some(())
     }
 
@@ -228,11 +871,15 @@ impl Store {
         child_id: NoteId,
         child_iteration: usize,
     ) -> Option<()> {
+        self.journal(id);
         let note = self.notes.get_mut(&id)?;
         let index = Self::resolve_child_iteration(&note.children, child_id, child_iteration)?;
         note.children.remove(index);
+        self.index.remove_parent(child_id, id);
+        note.touch();
+        self.dirty.insert(id);
 
-        self.make_consistent_and_tick();
+        self.tick();
         Some(())
     }
 
@@ -258,26 +905,545 @@ impl Store {
             to_idx -= 1;
         }
 
-        let removed_id = self
-            .notes
-            .get_mut(&from_id)
-            .unwrap()
-            .children
-            .remove(from_idx);
+        self.journal(from_id);
+        self.journal(to_id);
+        let from_note = self.notes.get_mut(&from_id).unwrap();
+        let removed_id = from_note.children.remove(from_idx);
         assert!(removed_id == child_id);
+        from_note.touch();
+        self.dirty.insert(from_id);
+
+        let to_note = self.notes.get_mut(&to_id).unwrap();
+        to_note.children.insert(to_idx, child_id);
+        to_note.touch();
+        self.dirty.insert(to_id);
+
+        self.index.remove_parent(child_id, from_id);
+        self.index.add_parent(child_id, to_id);
+
+        self.tick();
+        Some(())
+    }
+
+    /// The ids of a note and all of its descendants, each only once, in
+    /// breadth-first order.
+    pub fn subtree(&self, id: NoteId) -> Vec<NoteId> {
+        if !self.notes.contains_key(&id) {
+            return vec![];
+        }
+
+        let mut result = vec![];
+        let mut seen = HashSet::from([id]);
+        let mut queue = VecDeque::from([id]);
+        while let Some(id) = queue.pop_front() {
+            result.push(id);
+            for child in &self.notes[&id].children {
+                if seen.insert(*child) {
+                    queue.push_back(*child);
+                }
+            }
+        }
+        result
+    }
 
-        self.notes
-            .get_mut(&to_id)
-            .unwrap()
-            .children
-            .insert(to_idx, child_id);
+    /// The descendants of a note that can also be reached without it.
+    ///
+    /// These are the descendants that have parents outside the subtree, along
+    /// with their own descendants.
+    pub fn shared_descendants(&self, id: NoteId) -> HashSet<NoteId> {
+        let subtree = self.subtree(id);
+        let in_subtree = subtree.iter().copied().collect::<HashSet<_>>();
+
+        // Notes with a parent outside the subtree are reachable from
+        // elsewhere, and so is everything below them.
+        let mut kept = HashSet::new();
+        let mut queue = subtree
+            .iter()
+            .filter(|it| **it != id)
+            .filter(|it| {
+                self.index
+                    .parents
+                    .get(*it)
+                    .is_some_and(|parents| parents.keys().any(|p| !in_subtree.contains(p)))
+            })
+            .copied()
+            .collect::<VecDeque<_>>();
+        while let Some(note) = queue.pop_front() {
+            if note == id || !kept.insert(note) {
+                continue;
+            }
+            queue.extend(&self.notes[&note].children);
+        }
+        kept
+    }
+
+    /// Delete a note and all descendants that can't be reached without it.
+    ///
+    /// Descendants that also have parents outside the subtree are kept, along
+    /// with their own descendants, see [`Self::shared_descendants`]. Returns
+    /// the ids of the deleted notes.
+    pub fn delete_subtree(&mut self, id: NoteId) -> Vec<NoteId> {
+        let kept = self.shared_descendants(id);
+        let deleted = self
+            .subtree(id)
+            .into_iter()
+            .filter(|it| !kept.contains(it))
+            .collect::<Vec<_>>();
+        for note in &deleted {
+            self.delete(*note);
+        }
+        deleted
+    }
+
+    /// Copy a note and all of its descendants, keeping their ids.
+    pub fn extract_subtree(&self, id: NoteId) -> Option<Subtree> {
+        let ids = self.subtree(id);
+        if ids.is_empty() {
+            return None;
+        }
+
+        let reviews = ids
+            .iter()
+            .filter_map(|id| Some((*id, self.reviews.get(id)?.clone())))
+            .collect();
+        let notes = ids
+            .into_iter()
+            .map(|id| self.notes[&id].clone().save(id))
+            .collect();
+
+        Some(Subtree {
+            root: id,
+            notes,
+            reviews,
+        })
+    }
+
+    /// Add the notes of a subtree. Its root doesn't become a child of any
+    /// note, and children that don't exist are ignored.
+    ///
+    /// Fails without changing anything if any of the notes already exists,
+    /// even if only in the trash, or if the subtree contains a note twice.
+    pub fn insert_subtree(&mut self, subtree: Subtree) -> Option<()> {
+        let new = subtree.notes.iter().map(|it| it.id).collect::<HashSet<_>>();
+        if new.len() != subtree.notes.len() || new.iter().any(|id| self.is_used(*id)) {
+            return None;
+        }
 
-        self.make_consistent_and_tick();
+        for note in subtree.notes {
+            let id = note.id;
+            self.journal(id);
+            let mut note = RawNote::load(note);
+            note.children
+                .retain(|child| new.contains(child) || self.notes.contains_key(child));
+            self.index.add(id, &note);
+            self.notes.insert(id, note);
+            self.dirty.insert(id);
+        }
+        for (id, review) in subtree.reviews {
+            self.journal(id);
+            self.reviews.insert(id, review);
+            self.dirty.insert(id);
+        }
+
+        self.tick();
         Some(())
     }
 
+    /// Copy a note and all of its descendants with new ids.
+    ///
+    /// The copy of the note doesn't become a child of any note. Returns its
+    /// id.
+    pub fn duplicate_subtree(&mut self, id: NoteId) -> Option<NoteId> {
+        let subtree = self
+            .extract_subtree(id)?
+            .with_fresh_ids(|id| self.is_used(id));
+        let root = subtree.root;
+        self.insert_subtree(subtree)?;
+        Some(root)
+    }
+
+    /// Merge a note into another note and move it to the trash.
+    ///
+    /// The kept note gains the children of the removed note it doesn't already
+    /// have, as well as its properties, attachments and review state unless it
+    /// has its own. Tags and links of the removed note that are missing from
+    /// the kept note are appended to its text. The removed note is replaced by
+    /// the kept note in the children of its parents, and links to it are
+    /// changed to point to the kept note.
+    pub fn merge_notes(&mut self, keep: NoteId, remove: NoteId) -> Option<()> {
+        if keep == remove || !self.notes.contains_key(&keep) {
+            return None;
+        }
+        let removed = self.notes.get(&remove)?.clone();
+
+        // Parents of the removed note, which may include the kept note.
+        let parents = self
+            .index
+            .parents
+            .get(&remove)
+            .map(|it| it.keys().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for parent in parents {
+            self.journal(parent);
+            let has_keep = parent == keep || self.notes[&parent].children.contains(&keep);
+            let note = self.notes.get_mut(&parent).unwrap();
+            let mut replaced = false;
+            note.children.retain_mut(|child| {
+                if *child != remove {
+                    return true;
+                }
+                self.index.remove_parent(remove, parent);
+                if has_keep || replaced {
+                    return false;
+                }
+                *child = keep;
+                replaced = true;
+                true
+            });
+            if replaced {
+                self.index.add_parent(keep, parent);
+            }
+            note.touch();
+            self.dirty.insert(parent);
+        }
+
+        let sources = self
+            .index
+            .backlinks
+            .get(&remove)
+            .map(|it| it.iter().copied().collect::<Vec<_>>())
+            .unwrap_or_default();
+        for source in sources {
+            if source == remove {
+                continue;
+            }
+            self.journal(source);
+            let note = self.notes.get_mut(&source).unwrap();
+            self.index.remove_text(source, &note.text);
+            note.text = links::retarget(&note.text, remove, keep);
+            self.index.add_text(source, &note.text);
+            note.touch();
+            self.dirty.insert(source);
+        }
+
+        self.journal(keep);
+        let note = self.notes.get_mut(&keep).unwrap();
+        self.index.remove_text(keep, &note.text);
+        let tags = tags::parse(&note.text);
+        let links = links::parse(&note.text);
+        let mut missing = tags::parse(&removed.text)
+            .into_iter()
+            .filter(|tag| !tags.contains(tag))
+            .map(|tag| format!("#{tag}"))
+            .collect::<Vec<_>>();
+        missing.extend(
+            links::parse(&removed.text)
+                .into_iter()
+                .filter(|link| ![keep, remove].contains(link) && !links.contains(link))
+                .map(|link| format!("[[{link}]]")),
+        );
+        if !missing.is_empty() {
+            if !note.text.is_empty() {
+                note.text.push('\n');
+            }
+            note.text.push_str(&missing.join(" "));
+        }
+        self.index.add_text(keep, &note.text);
+
+        for child in &removed.children {
+            if ![keep, remove].contains(child) && !note.children.contains(child) {
+                note.children.push(*child);
+                self.index.add_parent(*child, keep);
+            }
+        }
+        for (key, value) in removed.meta.properties {
+            note.meta.properties.entry(key).or_insert(value);
+        }
+        for attachment in removed.meta.attachments {
+            if !note
+                .meta
+                .attachments
+                .iter()
+                .any(|it| it.name == attachment.name)
+            {
+                note.meta.attachments.push(attachment);
+            }
+        }
+        note.touch();
+        self.dirty.insert(keep);
+
+        if !self.reviews.contains_key(&keep)
+            && let Some(review) = self.reviews.get(&remove)
+        {
+            self.reviews.insert(keep, review.clone());
+        }
+
+        self.move_to_trash(remove, Timestamp::now());
+        self.tick();
+        Some(())
+    }
+
+    /// Apply several operations as a single update.
+    ///
+    /// The store's id increases at most once, no matter how many operations
+    /// are applied. If the closure returns an error, all of its changes are
+    /// rolled back. Transactions can be nested.
+    ///
+    /// Only the notes touched by the operations are remembered for rolling
+    /// back, so the cost doesn't depend on the size of the store. The store
+    /// keeps no undo history, so no undo entry is recorded. If it ever does,
+    /// the journal of a transaction contains everything a single entry needs.
+    pub fn transaction<T, E>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, E>) -> Result<T, E> {
+        let id = self.id;
+        let outer = self.journal.replace(Journal::new());
+
+        let result = f(self);
+        let journal = self.journal.take().unwrap();
+        self.journal = outer;
+
+        if result.is_err() {
+            self.roll_back(journal);
+            self.id = id;
+        } else {
+            // The outer transaction must be able to undo these changes too.
+            if let Some(outer) = &mut self.journal {
+                for (id, saved) in journal {
+                    outer.entry(id).or_insert(saved);
+                }
+            }
+            if self.id != id {
+                self.id = id;
+                self.tick();
+            }
+        }
+        result
+    }
+
+    /// Restore the notes of a journal to their saved state.
+    fn roll_back(&mut self, journal: Journal) {
+        // Remove the current notes first, so the index never contains both the
+        // current and the saved version of a note.
+        for id in journal.keys() {
+            if let Some(note) = self.notes.remove(id) {
+                self.index.remove(*id, &note);
+            }
+        }
+
+        for (id, saved) in journal {
+            if let Some(note) = saved.note {
+                self.index.add(id, &note);
+                self.notes.insert(id, note);
+            }
+            match saved.review {
+                Some(review) => self.reviews.insert(id, review),
+                None => self.reviews.remove(&id),
+            };
+            match saved.trashed {
+                Some(trashed) => self.trash.insert(id, trashed),
+                None => self.trash.remove(&id),
+            };
+            if saved.dirty {
+                self.dirty.insert(id);
+            } else {
+                self.dirty.remove(&id);
+            }
+        }
+    }
+
+    /// Move all notes to the trash.
     pub fn clear(&mut self) {
-        self.notes.clear();
-        self.make_consistent_and_tick();
+        let deleted = Timestamp::now();
+        let ids = self.ids().collect::<Vec<_>>();
+        for id in ids {
+            self.move_to_trash(id, deleted);
+        }
+        self.tick();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use rand::{Rng, SeedableRng, rngs::StdRng, seq::IndexedRandom};
+
+    use super::*;
+
+    fn rebuilt_index(store: &Store) -> Index {
+        let mut index = Index::default();
+        for (id, note) in &store.notes {
+            index.add(*id, note);
+        }
+        index
+    }
+
+    fn random_text(rng: &mut StdRng, ids: &[NoteId]) -> String {
+        let mut words = vec![];
+        for _ in 0..rng.random_range(0..4) {
+            match rng.random_range(0..3) {
+                0 => words.push(format!("#{}", ["a", "b", "c"].choose(rng).unwrap())),
+                1 if !ids.is_empty() => words.push(format!("[[{}]]", ids.choose(rng).unwrap())),
+                _ => words.push("word".to_string()),
+            }
+        }
+        words.join(" ")
+    }
+
+    /// Apply a random operation, which may or may not succeed.
+    fn random_step(store: &mut Store, rng: &mut StdRng) {
+        let mut ids = store.ids().collect::<Vec<_>>();
+        ids.sort_unstable();
+        let trash = store
+            .trash()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        let Some(&id) = ids.choose(rng) else {
+            let text = random_text(rng, &[]);
+            store.create(text);
+            return;
+        };
+        let other = *ids.choose(rng).unwrap();
+
+        match rng.random_range(0..14) {
+            0 | 1 => {
+                let text = random_text(rng, &ids);
+                store.create(text);
+            }
+            2 => {
+                store.delete(id);
+            }
+            3 => {
+                store.delete_subtree(id);
+            }
+            4 => {
+                let text = random_text(rng, &ids);
+                store.set_text(id, text);
+            }
+            5 | 6 => {
+                let position = rng.random_range(-3..3_i64) as isize;
+                store.add_child_at_position(id, other, position);
+            }
+            7 => {
+                let children = store.notes[&id].children.clone();
+                if let Some(child) = children.choose(rng) {
+                    store.remove_child_by_id(id, *child, 0);
+                }
+            }
+            8 => {
+                let children = store.notes[&id].children.clone();
+                if let Some(child) = children.choose(rng) {
+                    let position = rng.random_range(-3..3_i64) as isize;
+                    store.move_child_by_id_to_position(*child, id, 0, other, position);
+                }
+            }
+            9 => {
+                let children = (0..rng.random_range(0..4))
+                    .map(|_| *ids.choose(rng).unwrap())
+                    .collect();
+                store.set_children(id, children);
+            }
+            10 => {
+                store.merge_notes(id, other);
+            }
+            11 => {
+                if let Some(trashed) = trash.choose(rng) {
+                    store.restore(*trashed);
+                }
+            }
+            12 => {
+                store.duplicate_subtree(id);
+            }
+            _ => {
+                store.rename_tag("a", "b");
+            }
+        }
+    }
+
+    /// Everything a rolled back transaction must restore.
+    fn snapshot(store: &Store) -> String {
+        let mut repo = store.save();
+        repo.notes.sort_unstable_by_key(|it| it.id);
+        let reviews = repo.reviews.into_iter().collect::<BTreeMap<_, _>>();
+        let mut dirty = repo.dirty.unwrap().into_iter().collect::<Vec<_>>();
+        dirty.sort_unstable();
+        serde_json::to_string(&(repo.notes, reviews, repo.trash, dirty)).unwrap()
+    }
+
+    #[test]
+    fn saving_resets_dirty_notes() {
+        let mut store = Store::new();
+        let a = store.create("a".to_string());
+        let b = store.create("b".to_string());
+        assert_eq!(store.save().dirty, Some(HashSet::from([a, b])));
+
+        let oid = Oid::from_bytes(&[1; 20]).unwrap();
+        store.saved(oid);
+        let repo = store.save();
+        assert_eq!(repo.base, Some(oid));
+        assert_eq!(repo.dirty, Some(HashSet::new()));
+
+        store.set_text(b, "c".to_string());
+        assert_eq!(store.save().dirty, Some(HashSet::from([b])));
+    }
+
+    #[test]
+    fn incremental_index_matches_rebuild() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut store = Store::new();
+            for step in 0..300 {
+                random_step(&mut store, &mut rng);
+                assert_eq!(
+                    store.index,
+                    rebuilt_index(&store),
+                    "seed {seed}, step {step}"
+                );
+                for note in store.notes.values() {
+                    for child in &note.children {
+                        assert!(store.notes.contains_key(child), "seed {seed}, step {step}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn failed_transactions_change_nothing() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut store = Store::new();
+            for step in 0..100 {
+                let before = snapshot(&store);
+                let id = store.id();
+                let steps = rng.random_range(1..10);
+                let fail = rng.random_bool(0.5);
+                let result = store.transaction(|store| {
+                    for _ in 0..steps {
+                        random_step(store, &mut rng);
+                    }
+                    // Nested transactions that fail are rolled back on their
+                    // own, the outer transaction continues.
+                    let _ = store.transaction(|store| {
+                        random_step(store, &mut rng);
+                        Err::<(), ()>(())
+                    });
+                    if fail { Err(()) } else { Ok(()) }
+                });
+
+                assert_eq!(result.is_err(), fail);
+                if fail {
+                    assert_eq!(snapshot(&store), before, "seed {seed}, step {step}");
+                    assert_eq!(store.id(), id, "seed {seed}, step {step}");
+                } else {
+                    assert!(store.id() <= id + 1, "seed {seed}, step {step}");
+                }
+                assert_eq!(
+                    store.index,
+                    rebuilt_index(&store),
+                    "seed {seed}, step {step}"
+                );
+            }
+        }
     }
 }
@@ -0,0 +1,158 @@
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap, HashSet},
+};
+
+use crate::{
+    ids::NoteId,
+    repo::{Attachment, LazyRepo, Review},
+};
+
+use super::RawNote;
+
+/// How many notes a [`LazyStore`] keeps in memory by default.
+pub const DEFAULT_CACHE_CAPACITY: usize = 1024;
+
+/// The notes read most recently.
+struct Cache {
+    capacity: usize,
+    clock: u64,
+    entries: HashMap<NoteId, (u64, RawNote)>,
+    /// The ids of all entries by when they were last used, oldest first.
+    order: BTreeMap<u64, NoteId>,
+}
+
+impl Cache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            clock: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, id: NoteId) -> Option<RawNote> {
+        self.clock += 1;
+        let (used, note) = self.entries.get_mut(&id)?;
+        self.order.remove(used);
+        self.order.insert(self.clock, id);
+        *used = self.clock;
+        Some(note.clone())
+    }
+
+    fn insert(&mut self, id: NoteId, note: RawNote) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Some((used, _)) = self.entries.remove(&id) {
+            self.order.remove(&used);
+        } else if self.entries.len() >= self.capacity
+            && let Some((_, oldest)) = self.order.pop_first()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.clock += 1;
+        self.order.insert(self.clock, id);
+        self.entries.insert(id, (self.clock, note));
+    }
+}
+
+/// A read-only alternative to [`super::Store`] for large repos.
+///
+/// The structure of the notes is known up front, while the notes themselves
+/// are only read when requested and then kept in a cache of limited size.
+pub struct LazyStore {
+    repo: LazyRepo,
+    children: HashMap<NoteId, Vec<NoteId>>,
+    parents: HashMap<NoteId, HashSet<NoteId>>,
+    cache: RefCell<Cache>,
+}
+
+impl LazyStore {
+    pub fn load(repo: LazyRepo) -> Self {
+        Self::with_capacity(repo, DEFAULT_CACHE_CAPACITY)
+    }
+
+    pub fn with_capacity(repo: LazyRepo, capacity: usize) -> Self {
+        let ids = repo.ids().collect::<HashSet<_>>();
+
+        // Like in the store, children that don't exist are ignored.
+        let mut children = HashMap::new();
+        let mut parents = HashMap::<_, HashSet<_>>::new();
+        for id in &ids {
+            let note_children = repo
+                .children(*id)
+                .iter()
+                .filter(|child| ids.contains(child))
+                .copied()
+                .collect::<Vec<_>>();
+            for child in &note_children {
+                parents.entry(*child).or_default().insert(*id);
+            }
+            children.insert(*id, note_children);
+        }
+
+        Self {
+            repo,
+            children,
+            parents,
+            cache: RefCell::new(Cache::new(capacity)),
+        }
+    }
+
+    /// The ids of all notes, in no particular order.
+    pub fn ids(&self) -> impl Iterator<Item = NoteId> + '_ {
+        self.children.keys().copied()
+    }
+
+    pub fn contains(&self, id: NoteId) -> bool {
+        self.children.contains_key(&id)
+    }
+
+    /// The ids of all notes without parents, sorted by id.
+    pub fn roots(&self) -> Vec<NoteId> {
+        let mut roots = self
+            .ids()
+            .filter(|id| !self.parents.contains_key(id))
+            .collect::<Vec<_>>();
+        roots.sort_unstable();
+        roots
+    }
+
+    pub fn children(&self, id: NoteId) -> &[NoteId] {
+        self.children.get(&id).map_or(&[], |it| it)
+    }
+
+    pub fn parents(&self, id: NoteId) -> HashSet<NoteId> {
+        self.parents.get(&id).cloned().unwrap_or_default()
+    }
+
+    /// Read a note, or take it from the cache if it was read recently.
+    pub fn get(&self, id: NoteId) -> anyhow::Result<Option<RawNote>> {
+        if let Some(note) = self.cache.borrow_mut().get(id) {
+            return Ok(Some(note));
+        }
+        let Some(note) = self.repo.load_note(id)? else {
+            return Ok(None);
+        };
+        let mut note = RawNote::load(note);
+        note.children.clone_from(&self.children[&id]);
+        self.cache.borrow_mut().insert(id, note.clone());
+        Ok(Some(note))
+    }
+
+    /// Read the review state of a note. Reviews are not cached.
+    pub fn review(&self, id: NoteId) -> anyhow::Result<Option<Review>> {
+        self.repo.load_review(id)
+    }
+
+    /// Like [`super::Store::attachment`], but the attachment is returned by
+    /// value since the note may not stay in the cache.
+    pub fn attachment(&self, id: NoteId, name: &str) -> anyhow::Result<Option<Attachment>> {
+        let Some(note) = self.get(id)? else {
+            return Ok(None);
+        };
+        Ok(note.meta.attachments.into_iter().find(|it| it.name == name))
+    }
+}
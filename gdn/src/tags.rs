@@ -0,0 +1,64 @@
+use std::{collections::BTreeSet, ops::Range};
+
+fn is_tag_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '-' | '/')
+}
+
+/// Whether a string is a valid tag, without the leading `#`.
+pub fn is_valid(tag: &str) -> bool {
+    !tag.is_empty() && tag.chars().all(is_tag_char) && !tag.chars().all(|c| c.is_ascii_digit())
+}
+
+/// The byte ranges of all tags in a text, without the leading `#`.
+fn find(text: &str) -> Vec<Range<usize>> {
+    let mut result = vec![];
+    let mut prev = None;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((i, c)) = chars.next() {
+        let at_boundary = prev.is_none_or(|p: char| p.is_whitespace() || p == '(');
+        prev = Some(c);
+        if c != '#' || !at_boundary {
+            continue;
+        }
+
+        let start = i + c.len_utf8();
+        let mut end = start;
+        while let Some((j, c)) = chars.next_if(|(_, c)| is_tag_char(*c)) {
+            end = j + c.len_utf8();
+            prev = Some(c);
+        }
+
+        if is_valid(&text[start..end]) {
+            result.push(start..end);
+        }
+    }
+
+    result
+}
+
+/// All tags of a text, without the leading `#`.
+///
+/// Tags are words starting with a `#`, like `#todo` or `#project/gdn`. A tag
+/// must start at the beginning of the text, after whitespace or after an
+/// opening parenthesis. It consists of letters, digits, `_`, `-` and `/`, and
+/// must not consist of digits only, so `#1` is not a tag.
+pub fn parse(text: &str) -> BTreeSet<&str> {
+    find(text).into_iter().map(|range| &text[range]).collect()
+}
+
+/// Replace every occurrence of a tag in a text with a different tag.
+pub fn rename(text: &str, from: &str, to: &str) -> String {
+    let mut result = String::new();
+    let mut last = 0;
+    for range in find(text) {
+        if &text[range.clone()] != from {
+            continue;
+        }
+        result.push_str(&text[last..range.start]);
+        result.push_str(to);
+        last = range.end;
+    }
+    result.push_str(&text[last..]);
+    result
+}